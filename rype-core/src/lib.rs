@@ -0,0 +1,26 @@
+//! The typing engine behind rype, with no terminal attached.
+//!
+//! A [`TypingSession`] is fed keys with the time they were pressed and keeps
+//! track of the caret, mistakes and skipped words, and works out the result.
+//! Text generation, saving and drawing are left to the frontend.
+//!
+//! ```
+//! use std::time::Duration;
+//! use rype_core::{Key, Rules, TypingSession};
+//!
+//! let mut session = TypingSession::new("hi there", Rules::default());
+//! for (i, c) in "hi there".chars().enumerate() {
+//!     session.feed(Key::Char(c), Duration::from_millis(200 * i as u64));
+//! }
+//!
+//! assert!(session.progress().done);
+//! assert_eq!(session.result().unwrap().accuracy, 100.0);
+//! ```
+
+mod rules;
+mod session;
+pub mod stats;
+pub mod text;
+
+pub use rules::{Difficulty, Rules, StopOn};
+pub use session::{Key, Progress, TypingSession};
@@ -0,0 +1,78 @@
+use super::text::Comparison;
+
+/// Mistakes the caret won't move past.
+#[derive(Clone, Copy)]
+pub enum StopOn {
+    /// Wrong characters are rejected.
+    Letter,
+    /// Space won't move on from a word with a mistake in it.
+    Word,
+}
+
+impl StopOn {
+    pub fn name(self) -> &'static str {
+        match self {
+            StopOn::Letter => "letter",
+            StopOn::Word => "word",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "letter" => Some(StopOn::Letter),
+            "word" => Some(StopOn::Word),
+            _ => None
+        }
+    }
+}
+
+/// Mistakes that end the test.
+#[derive(Clone, Copy)]
+pub enum Difficulty {
+    Normal,
+    /// Fails on moving on from a word with a mistake in it.
+    Expert,
+    /// Fails on the first wrong key.
+    Master,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Normal => "normal",
+            Difficulty::Expert => "expert",
+            Difficulty::Master => "master",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "normal" => Some(Difficulty::Normal),
+            "expert" => Some(Difficulty::Expert),
+            "master" => Some(Difficulty::Master),
+            _ => None
+        }
+    }
+}
+
+/// How strictly a session takes mistakes.
+#[derive(Clone, Copy)]
+pub struct Rules {
+    pub stop_on: Option<StopOn>,
+    pub difficulty: Difficulty,
+    /// Whether backspace stops at words already typed correctly and moved
+    /// past.
+    pub lock_correct: bool,
+    pub comparison: Comparison,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            stop_on: None,
+            difficulty: Difficulty::Normal,
+            lock_correct: false,
+            comparison: Comparison::default(),
+        }
+    }
+}
@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+use super::rules::{Difficulty, Rules, StopOn};
+use super::stats::{Keystroke, TestResult};
+use super::text::{grapheme_ranges, graphemes, pop_grapheme, Comparison};
+
+// letters kept past the end of one word, any more are dropped
+const MAX_EXTRA_CHARS: usize = 20;
+
+/// A key that does something to a session.
+#[derive(Clone, Copy)]
+pub enum Key {
+    /// A typed character, `'\n'` for enter where the text has newlines.
+    Char(char),
+    Backspace,
+    /// Deletes back to the start of the word, or of the word before when at
+    /// the start of one.
+    DeleteWord,
+}
+
+/// How far a session has got.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    pub cursor: usize,
+    pub errors: usize,
+    pub done: bool,
+}
+
+/// One run through a text, from the first key to the result.
+///
+/// The caret moves a grapheme at a time. Every place in the text gets one
+/// grapheme of typed text: what was typed there, or `'\0'` where a word was
+/// skipped with space. Letters typed past the end of a word are kept apart,
+/// so they don't shift the rest of the text.
+pub struct TypingSession {
+    text: String,
+    /// Where each grapheme of `text` is, split once so keys don't have to.
+    places: Vec<Range<usize>>,
+    typed: String,
+    /// Where each place passed so far starts in `typed`, so the caret is
+    /// always its length.
+    typed_starts: Vec<usize>,
+    extra_chars: HashMap<usize, String>,
+    keystrokes: Vec<Keystroke>,
+    errors: usize,
+    last_key: Option<Duration>,
+    rules: Rules,
+    skips_indentation: bool,
+    word_target: Option<usize>,
+    /// When the session ended, and whether it was on a mistake.
+    finished: Option<(Duration, bool)>,
+}
+
+impl TypingSession {
+    pub fn new(text: &str, rules: Rules) -> Self {
+        TypingSession {
+            text: text.to_string(),
+            places: grapheme_ranges(text),
+            typed: String::new(),
+            typed_starts: vec![],
+            extra_chars: HashMap::new(),
+            keystrokes: vec![],
+            errors: 0,
+            last_key: None,
+            rules,
+            skips_indentation: false,
+            word_target: None,
+            finished: None,
+        }
+    }
+
+    /// Fills in leading indentation when a new line is reached, for code.
+    pub fn skip_indentation(mut self, skips: bool) -> Self {
+        self.skips_indentation = skips;
+        self
+    }
+
+    /// Ends the session once this many words are typed correctly.
+    pub fn word_target(mut self, target: Option<usize>) -> Self {
+        self.word_target = target;
+        self
+    }
+
+    /// Handles a key pressed `at` this long into the session. Keys after the
+    /// session has ended, or with no text to type, are ignored.
+    pub fn feed(&mut self, key: Key, at: Duration) {
+        if self.finished.is_some() || self.places.is_empty() {
+            return;
+        }
+
+        match key {
+            Key::Char(c) => self.on_char(c, at),
+            Key::Backspace => self.on_char('\x08', at),
+            Key::DeleteWord => self.delete_word(at),
+        }
+    }
+
+    /// Ends the session early, when time runs out or the frontend stops it.
+    pub fn finish(&mut self, at: Duration) {
+        if self.finished.is_none() {
+            self.finished = Some((at, false));
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            cursor: self.cursor(),
+            errors: self.errors,
+            done: self.finished.is_some(),
+        }
+    }
+
+    /// The result, once the session has ended.
+    pub fn result(&self) -> Option<TestResult> {
+        let (elapsed, _) = self.finished?;
+        let extra = self.extra_chars.values().map(|e| graphemes(e).len()).sum();
+        Some(TestResult::new(&self.text, &self.typed, extra, self.rules.comparison, &self.keystrokes, elapsed))
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Number of places in the text, one per grapheme.
+    pub fn text_len(&self) -> usize {
+        self.places.len()
+    }
+
+    /// The grapheme of the text at a place.
+    pub fn target(&self, i: usize) -> Option<&str> {
+        Some(&self.text[self.places.get(i)?.clone()])
+    }
+
+    /// What was typed so far, one grapheme per place in the text.
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    /// What was typed at a place, once the caret has passed it.
+    pub fn typed_at(&self, i: usize) -> Option<&str> {
+        let start = *self.typed_starts.get(i)?;
+        let end = self.typed_starts.get(i + 1).cloned().unwrap_or(self.typed.len());
+        Some(&self.typed[start..end])
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.typed_starts.len()
+    }
+
+    /// Letters typed past the end of a word, by the index of the space or
+    /// newline they were typed in front of.
+    pub fn extra_chars(&self) -> &HashMap<usize, String> {
+        &self.extra_chars
+    }
+
+    pub fn keystrokes(&self) -> &[Keystroke] {
+        &self.keystrokes
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Whether the session ended early on a mistake, under the strict
+    /// difficulties.
+    pub fn failed(&self) -> bool {
+        matches!(self.finished, Some((_, true)))
+    }
+
+    /// When the session ended.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.finished.map(|(at, _)| at)
+    }
+
+    /// Keys pressed that didn't match the text, even if fixed since.
+    pub fn error_count(&self) -> usize {
+        self.errors
+    }
+
+    /// Words that have been typed correctly and moved past.
+    pub fn correct_words(&self) -> usize {
+        let mut count = 0;
+        let mut correct = true;
+
+        for i in 0..self.cursor() {
+            if self.place(i) == " " {
+                if correct && !self.extra_chars.contains_key(&i) {
+                    count += 1;
+                }
+                correct = true;
+                continue;
+            }
+
+            if !self.rules.comparison.matches(self.typed_place(i), self.place(i)) {
+                correct = false;
+            }
+
+            // the last word has no space after it
+            if i + 1 == self.places.len() && correct {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    fn on_char(&mut self, c: char, at: Duration) {
+        // a space at the start of a word does nothing, so it isn't a
+        // keystroke either
+        if c == ' ' && self.at_word_start() {
+            return;
+        }
+
+        self.record_keystroke(c, at);
+
+        let mistake = self.is_mistake(c);
+        let wrong_word = self.submits_wrong_word(c);
+        let fails = match self.rules.difficulty {
+            Difficulty::Normal => false,
+            Difficulty::Expert => wrong_word,
+            Difficulty::Master => mistake,
+        };
+        if fails {
+            self.finished = Some((at, true));
+            return;
+        }
+
+        // a rejected key still counts against accuracy, it just doesn't
+        // move the caret
+        let rejected = match self.rules.stop_on {
+            Some(StopOn::Letter) => mistake,
+            Some(StopOn::Word) => wrong_word,
+            None => false
+        };
+        if rejected || (c == '\x08' && self.rules.lock_correct && self.after_correct_word()) {
+            return;
+        }
+
+        // the last letter can be left part typed, like one s of ß, and any
+        // key but the rest of it ends the text as it is
+        let completes = self.joins_last_grapheme(c) || self.completes_equivalent(c);
+        if self.cursor() == self.places.len() && c != '\x08' && !completes {
+            self.finished = Some((at, false));
+            return;
+        }
+
+        let cursor = self.cursor();
+        if c == '\x08' {
+            if let Some(extra) = self.extra_chars.get_mut(&cursor) {
+                pop_grapheme(extra);
+                if extra.is_empty() {
+                    self.extra_chars.remove(&cursor);
+                }
+            } else if cursor > 0 {
+                if self.skips_indentation {
+                    self.unskip_indentation();
+                }
+
+                // a skipped word is taken back in one go
+                let skipped = (0..self.cursor())
+                    .rev()
+                    .take_while(|i| self.typed_place(*i) == "\0")
+                    .count();
+                for _ in 0..skipped.max(1) {
+                    self.pop_place();
+                }
+            }
+        } else if c == ' ' {
+            if !is_separator(self.place(cursor)) {
+                while self.cursor() + 1 < self.places.len() && !is_separator(self.place(self.cursor() + 1)) {
+                    self.push_place("\0");
+                }
+                self.push_place("\0"); // last character on word
+                if self.cursor() < self.places.len() {
+                    self.push_place("\0"); // space fill
+                    if self.place(self.cursor() - 1) == "\n" {
+                        self.fill_indentation();
+                    }
+                }
+            } else {
+                self.push_place(" ");
+            }
+        } else if c == '\n' {
+            self.push_place("\n");
+            if self.place(cursor) == "\n" {
+                self.fill_indentation();
+            }
+        } else if self.joins_last_grapheme(c) {
+            // a combining mark typed on its own finishes the grapheme before
+            // the caret instead of taking a place of its own
+            self.typed.push(c);
+        } else if self.completes_equivalent(c) {
+            // a letter that is let go as two keys, like ss for ß, is filled
+            // in once both are typed, keeping one grapheme per place
+            let range = self.places[cursor - 1].clone();
+            self.pop_place();
+            self.typed_starts.push(self.typed.len());
+            self.typed.push_str(&self.text[range]);
+        } else if self.at_word_end() {
+            // letters past the end of a word are kept apart from the text,
+            // so they don't run into the next word
+            let extra = self.extra_chars.entry(cursor).or_default();
+            if graphemes(extra).len() < MAX_EXTRA_CHARS {
+                extra.push(c);
+            }
+        } else {
+            self.push_place(c.encode_utf8(&mut [0; 4]));
+        }
+
+        let target_reached = match self.word_target {
+            Some(n) => self.correct_words() >= n,
+            None => false
+        };
+
+        let text_done = self.cursor() >= self.places.len() && self.expected_char(self.rules.comparison).is_none();
+        if target_reached || text_done {
+            self.finished = Some((at, false));
+        }
+    }
+
+    // a run of backspaces, so replays and stats see it as one
+    fn delete_word(&mut self, at: Duration) {
+        let mut start = self.cursor();
+        while start > 0 && is_separator(self.place(start - 1)) {
+            start -= 1;
+        }
+        while start > 0 && !is_separator(self.place(start - 1)) {
+            start -= 1;
+        }
+
+        // extra letters are part of the word they were typed after
+        let position = |s: &TypingSession| (s.cursor(), s.typed.len(), s.extra_chars.get(&s.cursor()).map(String::len));
+        while self.cursor() > start || self.extra_chars.contains_key(&self.cursor()) {
+            let before = position(self);
+            self.on_char('\x08', at);
+            if position(self) == before {
+                break;
+            }
+        }
+    }
+
+    // the grapheme of the text at a place the caret can reach
+    fn place(&self, i: usize) -> &str {
+        &self.text[self.places[i].clone()]
+    }
+
+    fn at_word_start(&self) -> bool {
+        let cursor = self.cursor();
+        cursor < self.places.len()
+            && !is_separator(self.place(cursor))
+            && (cursor == 0 || is_separator(self.place(cursor - 1)))
+    }
+
+    // what was typed at a place the caret has passed
+    fn typed_place(&self, i: usize) -> &str {
+        let end = self.typed_starts.get(i + 1).cloned().unwrap_or(self.typed.len());
+        &self.typed[self.typed_starts[i]..end]
+    }
+
+    fn push_place(&mut self, g: &str) {
+        self.typed_starts.push(self.typed.len());
+        self.typed.push_str(g);
+    }
+
+    fn pop_place(&mut self) {
+        if let Some(start) = self.typed_starts.pop() {
+            self.typed.truncate(start);
+        }
+    }
+
+    // whether the caret is at the start of a word, with the word before it
+    // typed correctly
+    fn after_correct_word(&self) -> bool {
+        let i = self.cursor();
+        if i == 0 || !is_separator(self.place(i - 1)) || self.extra_chars.contains_key(&i) {
+            return false;
+        }
+
+        let mut start = i;
+        while start > 0 && is_separator(self.place(start - 1)) {
+            start -= 1;
+        }
+        let word_end = start;
+        while start > 0 && !is_separator(self.place(start - 1)) {
+            start -= 1;
+        }
+
+        !self.extra_chars.contains_key(&word_end)
+            && (start..i).all(|j| self.rules.comparison.matches(self.typed_place(j), self.place(j)))
+    }
+
+    fn joins_last_grapheme(&self, c: char) -> bool {
+        let i = self.cursor();
+        if i == 0 {
+            return false;
+        }
+
+        let joined = format!("{}{}", self.typed_place(i - 1), c);
+        graphemes(&joined).len() == 1
+    }
+
+    fn completes_equivalent(&self, c: char) -> bool {
+        let i = self.cursor();
+        if i == 0 || self.typed_place(i - 1) == self.place(i - 1) {
+            return false;
+        }
+
+        let together = format!("{}{}", self.typed_place(i - 1), c);
+        self.rules.comparison.fold(&together) == self.rules.comparison.fold(self.place(i - 1))
+    }
+
+    // whether the caret is on the space or newline right after a word
+    fn at_word_end(&self) -> bool {
+        let i = self.cursor();
+        i > 0 && i < self.places.len() && is_separator(self.place(i)) && !is_separator(self.place(i - 1))
+    }
+
+    // the char the next key should be, once both are folded by
+    // `comparison`: the rest of a grapheme typed a char at a time, or the
+    // start of the one under the caret
+    fn expected_char(&self, comparison: Comparison) -> Option<char> {
+        let i = self.cursor();
+
+        if i > 0 {
+            let (t, y) = (comparison.fold(self.place(i - 1)), comparison.fold(self.typed_place(i - 1)));
+            if !y.is_empty() && t.len() > y.len() && t.starts_with(&y) {
+                return t[y.len()..].chars().next();
+            }
+        }
+
+        comparison.fold(self.target(i)?).chars().next()
+    }
+
+    // a key that isn't the next character, spaces that skip included
+    fn is_mistake(&self, c: char) -> bool {
+        if c == '\x08' {
+            return false;
+        }
+
+        let folded = self.rules.comparison.fold(c.encode_utf8(&mut [0; 4]));
+        let mut chars = folded.chars();
+        !(chars.next() == self.expected_char(self.rules.comparison) && chars.next().is_none())
+    }
+
+    // whether the key ends the current word with a mistake in it: a space or
+    // enter after a wrong or unfinished word, or a key that finishes the
+    // text on a wrong word
+    fn submits_wrong_word(&self, c: char) -> bool {
+        let comparison = self.rules.comparison;
+        let i = self.cursor();
+        let start = (0..i)
+            .rev()
+            .find(|j| is_separator(self.place(*j)))
+            .map(|s| s + 1)
+            .unwrap_or(0);
+        let wrong_in = |places: Range<usize>| places
+            .into_iter()
+            .any(|j| !comparison.matches(self.typed_place(j), self.place(j)));
+
+        if c == '\x08' {
+            false
+        } else if i == self.places.len() {
+            // the rest of a part typed last letter
+            wrong_in(start..i - 1) || self.is_mistake(c)
+        } else if i + 1 == self.places.len() {
+            let folded = comparison.fold(self.place(i));
+            wrong_in(start..i)
+                || self.extra_chars.contains_key(&i)
+                || folded.is_empty()
+                || !folded.starts_with(&comparison.fold(c.encode_utf8(&mut [0; 4])))
+        } else if (c == ' ' || c == '\n') && i > start {
+            wrong_in(start..i) || self.extra_chars.contains_key(&i) || !is_separator(self.place(i))
+        } else {
+            false
+        }
+    }
+
+    fn record_keystroke(&mut self, c: char, at: Duration) {
+        // a key that is let go counts as the one it stands for, so it isn't
+        // taken as a mistake anywhere stats are kept
+        let expected = if c != '\x08' && !self.is_mistake(c) {
+            c
+        } else {
+            self.expected_char(Comparison::default()).unwrap_or('\0')
+        };
+        if c != '\x08' && c != expected {
+            self.errors += 1;
+        }
+        self.keystrokes.push(Keystroke {
+            index: self.cursor(),
+            expected,
+            actual: c,
+            latency: self.last_key.map(|t| at.saturating_sub(t)),
+        });
+        self.last_key = Some(at);
+    }
+
+    fn fill_indentation(&mut self) {
+        if !self.skips_indentation {
+            return;
+        }
+
+        while self.cursor() < self.places.len() && self.place(self.cursor()) == " " {
+            self.push_place(" ");
+        }
+    }
+
+    // backspacing out of skipped indentation takes the newline with it, so
+    // one backspace undoes one enter
+    fn unskip_indentation(&mut self) {
+        let cursor = self.cursor();
+        let line_start = (0..cursor)
+            .rev()
+            .find(|i| self.place(*i) == "\n")
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        let in_indentation = (line_start..cursor).all(|i| self.place(i) == " ");
+        if line_start == 0 || line_start == cursor || !in_indentation {
+            return;
+        }
+
+        while self.cursor() > line_start {
+            self.pop_place();
+        }
+    }
+}
+
+impl Default for TypingSession {
+    fn default() -> Self {
+        TypingSession::new("", Rules::default())
+    }
+}
+
+fn is_separator(g: &str) -> bool {
+    g == " " || g == "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Comparison;
+
+    fn typed(text: &str, keys: &[Key], rules: Rules) -> TypingSession {
+        let mut session = TypingSession::new(text, rules);
+        for (i, key) in keys.iter().enumerate() {
+            session.feed(*key, Duration::from_millis(100 * i as u64));
+        }
+        session
+    }
+
+    fn chars(keys: &str) -> Vec<Key> {
+        keys.chars()
+            .map(|c| match c {
+                '<' => Key::Backspace,
+                '^' => Key::DeleteWord,
+                c => Key::Char(c)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn space_skips_the_rest_of_a_word() {
+        let session = typed("one two three", &chars("on t"), Rules::default());
+        assert_eq!(session.typed(), "on\0\0t");
+        assert_eq!(session.cursor(), 5);
+    }
+
+    #[test]
+    fn backspace_takes_back_a_skipped_word_at_once() {
+        let session = typed("one two three", &chars("on <"), Rules::default());
+        assert_eq!(session.typed(), "on");
+        assert_eq!(session.cursor(), 2);
+    }
+
+    #[test]
+    fn skipped_indentation_goes_with_its_newline() {
+        let keys = chars("a\nb<<");
+        let mut session = TypingSession::new("a\n    b\nc", Rules::default()).skip_indentation(true);
+        for key in &keys[..3] {
+            session.feed(*key, Duration::ZERO);
+        }
+        assert_eq!(session.typed(), "a\n    b");
+
+        for key in &keys[3..] {
+            session.feed(*key, Duration::ZERO);
+        }
+        assert_eq!(session.typed(), "a");
+    }
+
+    #[test]
+    fn delete_word_goes_back_to_the_start_of_the_word() {
+        let session = typed("one two three", &chars("one tw^"), Rules::default());
+        assert_eq!(session.typed(), "one ");
+    }
+
+    #[test]
+    fn delete_word_at_a_word_start_takes_the_word_before() {
+        let session = typed("one two three", &chars("one two ^"), Rules::default());
+        assert_eq!(session.typed(), "one ");
+    }
+
+    #[test]
+    fn lock_correct_keeps_correct_words() {
+        let rules = Rules { lock_correct: true, ..Rules::default() };
+        let session = typed("one two three", &chars("one two th^^"), rules);
+        assert_eq!(session.typed(), "one two ");
+
+        let session = typed("one two three", &chars("one twx ^"), rules);
+        assert_eq!(session.typed(), "one ");
+    }
+
+    #[test]
+    fn extra_letters_are_kept_apart() {
+        let session = typed("one two", &chars("onexx t"), Rules::default());
+        assert_eq!(session.typed(), "one t");
+        assert_eq!(session.extra_chars().get(&3).map(String::as_str), Some("xx"));
+
+        let session = typed("one two", &chars("onexx<<"), Rules::default());
+        assert!(session.extra_chars().is_empty());
+        assert_eq!(session.typed(), "one");
+    }
+
+    #[test]
+    fn extra_letters_are_capped() {
+        let keys = format!("one{}", "x".repeat(MAX_EXTRA_CHARS + 5));
+        let session = typed("one two", &chars(&keys), Rules::default());
+        assert_eq!(session.extra_chars()[&3].len(), MAX_EXTRA_CHARS);
+    }
+
+    #[test]
+    fn stop_on_letter_rejects_wrong_keys() {
+        let rules = Rules { stop_on: Some(StopOn::Letter), ..Rules::default() };
+        let session = typed("ab cd", &chars("axqzb cd"), rules);
+        assert!(session.is_finished());
+        assert_eq!(session.typed(), "ab cd");
+        assert_eq!(session.progress().errors, 3);
+        assert_eq!(session.result().unwrap().accuracy, 62.5);
+    }
+
+    #[test]
+    fn stop_on_word_keeps_the_caret_on_a_wrong_word() {
+        let rules = Rules { stop_on: Some(StopOn::Word), ..Rules::default() };
+        let session = typed("ab cd", &chars("ax "), rules);
+        assert_eq!(session.typed(), "ax");
+    }
+
+    #[test]
+    fn expert_fails_on_moving_past_a_wrong_word() {
+        let rules = Rules { difficulty: Difficulty::Expert, ..Rules::default() };
+        let session = typed("ab cd", &chars("ax"), rules);
+        assert!(!session.is_finished());
+
+        let session = typed("ab cd", &chars("ax "), rules);
+        assert!(session.failed());
+    }
+
+    #[test]
+    fn master_fails_on_the_first_wrong_key() {
+        let rules = Rules { difficulty: Difficulty::Master, ..Rules::default() };
+        let session = typed("ab cd", &chars("ax"), rules);
+        assert!(session.failed());
+        assert_eq!(session.result().unwrap().accuracy, 50.0);
+    }
+
+    #[test]
+    fn space_at_the_start_of_a_word_is_ignored() {
+        let session = typed("one two", &chars("one  two"), Rules::default());
+        assert_eq!(session.typed(), "one two");
+        assert_eq!(session.error_count(), 0);
+        assert_eq!(session.keystrokes().len(), 7);
+        assert_eq!(session.result().unwrap().accuracy, 100.0);
+
+        let rules = Rules { difficulty: Difficulty::Master, ..Rules::default() };
+        let session = typed("one two", &chars("one  two"), rules);
+        assert!(!session.failed());
+        assert_eq!(session.typed(), "one two");
+    }
+
+    #[test]
+    fn combining_marks_join_the_letter_before() {
+        let session = typed("cafe\u{301} ok", &chars("cafe\u{301} ok"), Rules::default());
+        assert_eq!(session.progress().errors, 0);
+        assert_eq!(session.typed(), "cafe\u{301} ok");
+    }
+
+    #[test]
+    fn a_letter_typed_as_two_keys_finishes_the_text() {
+        let rules = Rules { comparison: Comparison { accents: true, ..Comparison::default() }, ..Rules::default() };
+        let session = typed("groß", &chars("gros"), rules);
+        assert!(!session.is_finished());
+
+        let session = typed("groß", &chars("gross"), rules);
+        assert!(session.is_finished());
+        assert_eq!(session.typed(), "groß");
+        assert_eq!(session.result().unwrap().accuracy, 100.0);
+    }
+
+    #[test]
+    fn word_target_ends_the_session() {
+        let mut session = TypingSession::new("one two three", Rules::default()).word_target(Some(2));
+        for c in "one two ".chars() {
+            session.feed(Key::Char(c), Duration::ZERO);
+        }
+        assert!(session.is_finished());
+        assert_eq!(session.correct_words(), 2);
+    }
+
+    #[test]
+    fn keys_after_the_end_are_ignored() {
+        let session = typed("ab", &chars("abcd"), Rules::default());
+        assert_eq!(session.keystrokes().len(), 2);
+    }
+
+    #[test]
+    fn empty_text_takes_no_keys() {
+        let session = typed("", &chars(" a\n<^"), Rules::default());
+        assert!(session.keystrokes().is_empty());
+        assert!(!session.is_finished());
+
+        let mut session = TypingSession::default();
+        session.finish(Duration::ZERO);
+        assert_eq!(session.result().unwrap().accuracy, 0.0);
+    }
+}
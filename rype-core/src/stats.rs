@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::text::{graphemes, Comparison};
+
+/// A single key press during a test.
+pub struct Keystroke {
+    /// Position of the caret when the key was pressed.
+    pub index: usize,
+    /// The character under the caret when the key was pressed.
+    pub expected: char,
+    /// The character typed, `'\x08'` for backspace.
+    pub actual: char,
+    /// Time since the previous key press, `None` for the first one.
+    pub latency: Option<Duration>,
+}
+
+/// How one key was typed over a test.
+#[derive(Default)]
+pub struct KeyStats {
+    pub presses: usize,
+    pub errors: usize,
+    pub total_latency: Duration,
+    pub timed_presses: usize,
+}
+
+impl KeyStats {
+    pub fn error_rate(&self) -> f64 {
+        if self.presses > 0 {
+            self.errors as f64 / self.presses as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.timed_presses > 0 {
+            Some(self.total_latency / self.timed_presses as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn add(&mut self, keystroke: &Keystroke) {
+        self.presses += 1;
+        if keystroke.actual != keystroke.expected {
+            self.errors += 1;
+        }
+        if let Some(latency) = keystroke.latency {
+            self.total_latency += latency;
+            self.timed_presses += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &KeyStats) {
+        self.presses += other.presses;
+        self.errors += other.errors;
+        self.total_latency += other.total_latency;
+        self.timed_presses += other.timed_presses;
+    }
+}
+
+/// Groups keystrokes by the character that should have been typed.
+pub fn key_stats(keystrokes: &[Keystroke]) -> HashMap<char, KeyStats> {
+    let mut stats: HashMap<char, KeyStats> = HashMap::new();
+
+    // backspaces don't say anything about the key they land on
+    for k in keystrokes.iter().filter(|k| k.actual != '\x08') {
+        stats.entry(k.expected).or_default().add(k);
+    }
+
+    stats
+}
+
+/// Groups keystrokes by the pair of characters that should have been typed,
+/// counting each press towards the pair it finishes.
+pub fn bigram_stats(keystrokes: &[Keystroke]) -> HashMap<(char, char), KeyStats> {
+    let mut stats: HashMap<(char, char), KeyStats> = HashMap::new();
+    let mut previous = None;
+
+    // a backspace breaks the run, the next press isn't really a pair with
+    // whatever came before it
+    for k in keystrokes {
+        if k.actual == '\x08' {
+            previous = None;
+            continue;
+        }
+
+        if let Some(p) = previous {
+            stats.entry((p, k.expected)).or_default().add(k);
+        }
+        previous = Some(k.expected);
+    }
+
+    stats
+}
+
+/// Summary of a finished test, shown on the results screen.
+pub struct TestResult {
+    pub wpm: f64,
+    pub raw_wpm: f64,
+    /// Percentage of key presses that were right, backspaces aside.
+    pub accuracy: f64,
+    pub correct: usize,
+    pub incorrect: usize,
+    /// Letters typed past the end of a word.
+    pub extra: usize,
+    pub skipped: usize,
+    pub elapsed: Duration,
+    pub keys: HashMap<char, KeyStats>,
+}
+
+impl TestResult {
+    pub fn new(game_text: &str, my_game_text: &str, extra: usize, comparison: Comparison, keystrokes: &[Keystroke], elapsed: Duration) -> Self {
+        let mut correct = 0;
+        let mut incorrect = 0;
+        let mut skipped = 0;
+
+        for (g, target) in graphemes(my_game_text).into_iter().zip(graphemes(game_text)) {
+            if g == "\0" {
+                skipped += 1;
+            } else if comparison.matches(g, target) {
+                correct += 1;
+            } else {
+                incorrect += 1;
+            }
+        }
+
+        // a "word" is five characters, spaces included
+        let minutes = elapsed.as_secs_f64() / 60.0;
+        let per_minute = |chars: usize| if minutes > 0.0 {
+            chars as f64 / 5.0 / minutes
+        } else {
+            0.0
+        };
+
+        // accuracy goes by every key pressed, so mistakes that were fixed,
+        // rejected, or ended the test still count
+        let pressed = keystrokes.iter().filter(|k| k.actual != '\x08').count();
+        let wrong = keystrokes.iter().filter(|k| k.actual != '\x08' && k.actual != k.expected).count();
+        let accuracy = if pressed > 0 {
+            (pressed - wrong) as f64 / pressed as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        TestResult {
+            wpm: per_minute(correct),
+            raw_wpm: per_minute(correct + incorrect + extra),
+            accuracy,
+            correct,
+            incorrect,
+            extra,
+            skipped,
+            elapsed,
+            keys: key_stats(keystrokes),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::ops::Range;
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Differences between what was typed and the text that are let go, from
+/// `--ignore`.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Comparison {
+    /// é is typed as e, ß as ss.
+    pub accents: bool,
+    pub case: bool,
+    /// Curly quotes and dashes are typed as their plain ascii versions.
+    pub quotes: bool,
+}
+
+impl Comparison {
+    pub fn from_names(names: &[String]) -> Option<Self> {
+        let mut comparison = Comparison::default();
+        for name in names {
+            match name.as_str() {
+                "accents" => comparison.accents = true,
+                "case" => comparison.case = true,
+                "quotes" => comparison.quotes = true,
+                _ => return None
+            }
+        }
+        Some(comparison)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        [("accents", self.accents), ("case", self.case), ("quotes", self.quotes)]
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    /// `text` with every difference that is let go taken out, so that two
+    /// strings typed the same way fold to the same thing.
+    pub fn fold(&self, text: &str) -> String {
+        let mut out = String::new();
+        for c in text.chars() {
+            let c = if self.quotes { plain_quote(c) } else { c };
+            if !self.accents {
+                out.push(c);
+                continue;
+            }
+
+            match base_letters(c) {
+                Some(s) => out.push_str(s),
+                None => out.extend(c.to_string().nfd().filter(|c| !is_combining_mark(*c)))
+            }
+        }
+
+        if self.case {
+            out.to_lowercase()
+        } else {
+            out
+        }
+    }
+
+    /// Whether `typed` counts as the grapheme `target`.
+    pub fn matches(&self, typed: &str, target: &str) -> bool {
+        typed == target || (*self != Comparison::default() && self.fold(typed) == self.fold(target))
+    }
+}
+
+// letters that don't decompose into a base letter and accents
+fn base_letters(c: char) -> Option<&'static str> {
+    match c {
+        'ß' => Some("ss"),
+        'ẞ' => Some("SS"),
+        'æ' => Some("ae"),
+        'Æ' => Some("AE"),
+        'œ' => Some("oe"),
+        'Œ' => Some("OE"),
+        'ø' => Some("o"),
+        'Ø' => Some("O"),
+        'đ' | 'ð' => Some("d"),
+        'Đ' | 'Ð' => Some("D"),
+        'ł' => Some("l"),
+        'Ł' => Some("L"),
+        'þ' => Some("th"),
+        'Þ' => Some("TH"),
+        'ı' => Some("i"),
+        _ => None
+    }
+}
+
+fn plain_quote(c: char) -> char {
+    match c {
+        '‘' | '’' | '‚' | '‛' | '′' => '\'',
+        '“' | '”' | '„' | '‟' | '″' => '"',
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => '-',
+        c => c
+    }
+}
+
+/// Splits `text` into what reads as single characters, extended grapheme
+/// clusters, so accents and emoji made of several chars are typed, compared
+/// and drawn as one.
+pub fn graphemes(text: &str) -> Vec<&str> {
+    text.graphemes(true).collect()
+}
+
+/// Byte ranges of the graphemes of `text`, for indexing into it without
+/// splitting it again.
+pub fn grapheme_ranges(text: &str) -> Vec<Range<usize>> {
+    text.grapheme_indices(true).map(|(i, g)| i..i + g.len()).collect()
+}
+
+/// Removes the last grapheme of `text`.
+pub fn pop_grapheme(text: &mut String) {
+    if let Some((i, _)) = text.grapheme_indices(true).next_back() {
+        text.truncate(i);
+    }
+}
+
+/// Columns `grapheme` takes up on screen. Wide characters take two, and
+/// nothing takes less than one so the caret always has a cell to sit in.
+pub fn width(grapheme: &str) -> usize {
+    grapheme.width().max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_lets_go_of_accents() {
+        let comparison = Comparison { accents: true, ..Comparison::default() };
+        assert_eq!(comparison.fold("café"), "cafe");
+        assert_eq!(comparison.fold("cafe\u{301}"), "cafe");
+        assert_eq!(comparison.fold("Straße"), "Strasse");
+        assert_eq!(comparison.fold("Ærø"), "AEro");
+    }
+
+    #[test]
+    fn fold_lets_go_of_case_and_quotes() {
+        let comparison = Comparison { case: true, quotes: true, ..Comparison::default() };
+        assert_eq!(comparison.fold("“Don’t” — OK"), "\"don't\" - ok");
+    }
+
+    #[test]
+    fn fold_keeps_everything_by_default() {
+        assert_eq!(Comparison::default().fold("Café “x”"), "Café “x”");
+        assert!(!Comparison::default().matches("e", "é"));
+    }
+
+    #[test]
+    fn graphemes_keep_clusters_together() {
+        assert_eq!(graphemes("e\u{301}👍🏽a"), vec!["e\u{301}", "👍🏽", "a"]);
+        assert_eq!(grapheme_ranges("e\u{301}a"), vec![0..3, 3..4]);
+    }
+}
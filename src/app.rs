@@ -1,45 +1,142 @@
+use std::io;
 use tui::Terminal;
 use tui::backend::Backend;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rype_core::{Key, Rules, TypingSession};
+use rype_core::stats::TestResult;
+use rype_core::text::Comparison;
 
-pub struct App<'a> {
+use super::timer::Timer;
+use super::history::{History, Record, TestConfig};
+use super::wordlist::WordList;
+use super::quotes::Quote;
+use super::snippets::Snippet;
+use super::customtext::CustomText;
+use super::modifiers::{add_numbers, add_punctuation};
+use super::training::{KeyHistory, Lesson};
+use super::drill::{typed_words, ProblemWords};
+use super::daily;
+use super::replay::Replay;
+use super::ghost::Ghost;
+use super::cli::GhostSource;
+use super::race::{Race, RaceEvent};
+use super::ui::TypingLayout;
+
+const DRILL_WORDS: usize = 12;
+const DRILL_REPEATS: usize = 3;
+// the daily test draws from this many of the most common words
+const DAILY_WORDS: usize = 1000;
+
+pub struct App {
     pub should_quit: bool,
     pub state: GameState,
     pub focused_window: FocusedWindow,
+    pub selected_mode_tab: usize,
     pub selected_game_tab: usize,
     pub selected_timer_tab: usize,
-    pub game_options: Vec<&'a str>,
-    pub timer_options: Vec<&'a str>,
-    pub words: Vec<&'a str>,
-    pub my_game_text: String,
+    pub selected_modifier_tab: usize,
+    pub mode_options: Vec<&'static str>,
+    pub game_options: Vec<&'static str>,
+    pub timer_options: Vec<&'static str>,
+    pub modifier_options: Vec<&'static str>,
+    pub enabled_modifiers: Vec<&'static str>,
+    pub word_lists: Vec<WordList>,
+    pub selected_word_list: usize,
+    pub quotes: Vec<Quote>,
+    pub current_quote: Option<usize>,
+    pub snippets: Vec<Snippet>,
+    pub custom_text: Option<CustomText>,
     pub game_text: String,
-    pub cursor_index: usize,
+    /// What has been typed of `game_text`, replaced when a test starts.
+    pub session: TypingSession,
+    /// Number of lines shown in the typing viewport.
+    pub typing_lines: usize,
+    /// How the typing text was last laid out, reused while it still fits.
+    pub typing_layout: Option<TypingLayout>,
+    pub timer: Option<Timer>,
+    /// Seconds shown in the timer chunk, counting down in time mode and up otherwise.
+    pub clock: u16,
+    pub result: Option<TestResult>,
+    pub heatmap: Heatmap,
+    pub history: History,
+    pub key_history: KeyHistory,
+    pub problem_words: ProblemWords,
+    /// Letters and bigrams the current training text is built around.
+    pub lesson: Option<Lesson>,
+    pub seed: u64,
+    /// Seed every test uses instead of a random one, from `--seed`.
+    pub fixed_seed: Option<u64>,
+    /// Whether the current test is a replay being played back, which is
+    /// never saved.
+    pub replaying: bool,
+    pub ghost_source: Option<GhostSource>,
+    /// Ghost caret for the current test, if there is one to race.
+    pub ghost: Option<Ghost>,
+    pub race: Option<Race>,
+    /// How strictly mistakes are taken, from `--stop-on`, `--difficulty`,
+    /// `--lock-correct` and `--ignore`.
+    pub rules: Rules,
 }
 
-impl<'a> App<'a> {
-    pub fn new(words: Vec<&'a str>) -> Self {
+impl App {
+    pub fn new(word_lists: Vec<WordList>, selected_word_list: usize, quotes: Vec<Quote>, snippets: Vec<Snippet>, history: History, key_history: KeyHistory, problem_words: ProblemWords) -> Self {
         let mut a = App {
             should_quit: false,
             state: GameState::Pre,
             focused_window: FocusedWindow::Game,
+            selected_mode_tab: 0,
             selected_game_tab: 0,
             selected_timer_tab: 0,
-            game_options: vec!["100", "1k", "5k", "10k"],
-            timer_options: vec!["30", "60", "120"],
-            my_game_text: "".to_string(),
-            words,
+            selected_modifier_tab: 0,
+            mode_options: vec!["time", "words", "quote", "code", "train", "drill", "daily"],
+            game_options: vec![],
+            timer_options: vec![],
+            modifier_options: vec![],
+            enabled_modifiers: vec![],
+            word_lists,
+            selected_word_list,
+            quotes,
+            current_quote: None,
+            snippets,
+            custom_text: None,
             game_text: "".to_string(),
-            cursor_index: 0,
+            session: TypingSession::default(),
+            typing_lines: 3,
+            typing_layout: None,
+            timer: None,
+            clock: 0,
+            result: None,
+            heatmap: Heatmap::Errors,
+            history,
+            key_history,
+            problem_words,
+            lesson: None,
+            seed: 0,
+            fixed_seed: None,
+            replaying: false,
+            ghost_source: None,
+            ghost: None,
+            race: None,
+            rules: Rules::default(),
         };
 
+        a.game_options = a.mode_game_options();
+        a.timer_options = a.mode_timer_options();
+        a.modifier_options = a.mode_modifier_options();
         a.game_text = a.gen_test();
+        a.clock = a.start_clock();
         a
     }
 
     pub fn cycle_focus_forward(&mut self) {
         match self.focused_window {
-            FocusedWindow::Game => self.focused_window = FocusedWindow::GameOptions,
-            FocusedWindow::GameOptions => self.focused_window = FocusedWindow::TimerOptions,
+            FocusedWindow::Game => self.focused_window = FocusedWindow::ModeOptions,
+            FocusedWindow::ModeOptions => self.focused_window = FocusedWindow::GameOptions,
+            FocusedWindow::GameOptions => self.focused_window = FocusedWindow::WordListOptions,
+            FocusedWindow::WordListOptions => self.focused_window = FocusedWindow::ModifierOptions,
+            FocusedWindow::ModifierOptions => self.focused_window = FocusedWindow::TimerOptions,
             FocusedWindow::TimerOptions => self.focused_window = FocusedWindow::Game
         }
     }
@@ -47,13 +144,26 @@ impl<'a> App<'a> {
     pub fn cycle_focus_backward(&mut self) {
         match self.focused_window {
             FocusedWindow::Game => self.focused_window = FocusedWindow::TimerOptions,
-            FocusedWindow::GameOptions => self.focused_window = FocusedWindow::Game,
-            FocusedWindow::TimerOptions => self.focused_window = FocusedWindow::GameOptions
+            FocusedWindow::ModeOptions => self.focused_window = FocusedWindow::Game,
+            FocusedWindow::GameOptions => self.focused_window = FocusedWindow::ModeOptions,
+            FocusedWindow::WordListOptions => self.focused_window = FocusedWindow::GameOptions,
+            FocusedWindow::ModifierOptions => self.focused_window = FocusedWindow::WordListOptions,
+            FocusedWindow::TimerOptions => self.focused_window = FocusedWindow::ModifierOptions
         }
     }
 
     pub fn cycle_tab_forward(&mut self) {
         match self.focused_window {
+            FocusedWindow::ModeOptions => {
+                let tabs_length = self.mode_options.len();
+                if self.selected_mode_tab + 1 < tabs_length {
+                    self.selected_mode_tab += 1;
+                } else {
+                    self.selected_mode_tab = 0;
+                }
+
+                self.on_mode_change();
+            },
             FocusedWindow::GameOptions => {
                 let tabs_length = self.game_options.len();
                 if self.selected_game_tab + 1 < tabs_length {
@@ -64,6 +174,24 @@ impl<'a> App<'a> {
 
                 self.game_text = self.gen_test();
             },
+            FocusedWindow::WordListOptions => {
+                let tabs_length = self.word_lists.len();
+                if self.selected_word_list + 1 < tabs_length {
+                    self.selected_word_list += 1;
+                } else {
+                    self.selected_word_list = 0;
+                }
+
+                self.game_text = self.gen_test();
+            },
+            FocusedWindow::ModifierOptions => {
+                let tabs_length = self.modifier_options.len();
+                if self.selected_modifier_tab + 1 < tabs_length {
+                    self.selected_modifier_tab += 1;
+                } else {
+                    self.selected_modifier_tab = 0;
+                }
+            },
             FocusedWindow::TimerOptions => {
                 let tabs_length = self.timer_options.len();
                 if self.selected_timer_tab + 1 < tabs_length {
@@ -71,6 +199,8 @@ impl<'a> App<'a> {
                 } else {
                     self.selected_timer_tab = 0;
                 }
+
+                self.clock = self.start_clock();
             },
             _ => {}
         }
@@ -78,6 +208,16 @@ impl<'a> App<'a> {
 
     pub fn cycle_tab_backward(&mut self) {
         match self.focused_window {
+            FocusedWindow::ModeOptions => {
+                let tabs_length = self.mode_options.len();
+                if self.selected_mode_tab > 0 {
+                    self.selected_mode_tab -= 1;
+                } else {
+                    self.selected_mode_tab = tabs_length - 1;
+                }
+
+                self.on_mode_change();
+            },
             FocusedWindow::GameOptions => {
                 let tabs_length = self.game_options.len();
                 if self.selected_game_tab > 0 {
@@ -88,6 +228,24 @@ impl<'a> App<'a> {
 
                 self.game_text = self.gen_test();
             },
+            FocusedWindow::WordListOptions => {
+                let tabs_length = self.word_lists.len();
+                if self.selected_word_list > 0 {
+                    self.selected_word_list -= 1;
+                } else {
+                    self.selected_word_list = tabs_length - 1;
+                }
+
+                self.game_text = self.gen_test();
+            },
+            FocusedWindow::ModifierOptions => {
+                let tabs_length = self.modifier_options.len();
+                if self.selected_modifier_tab > 0 {
+                    self.selected_modifier_tab -= 1;
+                } else {
+                    self.selected_modifier_tab = tabs_length - 1;
+                }
+            },
             FocusedWindow::TimerOptions => {
                 let tabs_length = self.timer_options.len();
                 if self.selected_timer_tab > 0 {
@@ -95,95 +253,541 @@ impl<'a> App<'a> {
                 } else {
                     self.selected_timer_tab = tabs_length - 1;
                 }
+
+                self.clock = self.start_clock();
             },
             _ => {}
         }
     }
 
-    pub fn start_game<B: Backend> (&mut self, term: &mut Terminal<B>) {
-        match term.clear() {
-            Ok(()) => {},
-            Err(error) => {
-                panic!("error clearing terminal: {:?}", error);
+    pub fn toggle_modifier(&mut self) {
+        if let FocusedWindow::ModifierOptions = self.focused_window {
+            let modifier = self.modifier_options[self.selected_modifier_tab];
+            if self.modifier_enabled(modifier) {
+                self.enabled_modifiers.retain(|m| *m != modifier);
+            } else {
+                self.enabled_modifiers.push(modifier);
             }
+
+            self.game_text = self.gen_test();
+        }
+    }
+
+    pub fn modifier_enabled(&self, modifier: &str) -> bool {
+        self.enabled_modifiers.contains(&modifier)
+    }
+
+    /// Adds a custom mode for the given text and switches to it.
+    pub fn set_custom_text(&mut self, custom_text: CustomText) {
+        self.custom_text = Some(custom_text);
+        self.mode_options.push("custom");
+        self.selected_mode_tab = self.mode_options.len() - 1;
+        self.on_mode_change();
+    }
+
+    /// Sets up a test the way the replay was typed, ready to be played back.
+    pub fn load_replay(&mut self, replay: &Replay) -> Result<(), String> {
+        self.load_test(&replay.config, replay.seed, &replay.text)?;
+        self.rules.stop_on = replay.stop_on;
+        self.rules.difficulty = replay.difficulty;
+        self.rules.lock_correct = replay.lock_correct;
+        self.replaying = true;
+        Ok(())
+    }
+
+    /// Sets up the given settings and text, for tests that were generated
+    /// somewhere else.
+    pub fn load_test(&mut self, config: &TestConfig, seed: u64, text: &str) -> Result<(), String> {
+        let custom_text = CustomText {
+            name: config.game_option.clone(),
+            text: text.to_string(),
+        };
+        match self.mode_options.iter().position(|m| *m == config.mode) {
+            Some(i) if config.mode == "custom" => {
+                self.custom_text = Some(custom_text);
+                self.selected_mode_tab = i;
+                self.on_mode_change();
+            },
+            None if config.mode == "custom" => self.set_custom_text(custom_text),
+            Some(i) => {
+                self.selected_mode_tab = i;
+                self.on_mode_change();
+            },
+            None => return Err(format!("unknown mode: {}", config.mode))
+        }
+
+        // options that no longer exist are left as they are, the text is
+        // what matters
+        if let Some(i) = self.game_options.iter().position(|o| *o == config.game_option) {
+            self.selected_game_tab = i;
+        }
+        if let Some(i) = self.timer_options.iter().position(|o| *o == config.timer_option) {
+            self.selected_timer_tab = i;
         }
+        if let Some(i) = self.word_lists.iter().position(|l| l.name == config.word_list) {
+            self.selected_word_list = i;
+        }
+        self.enabled_modifiers = self.modifier_options
+            .iter()
+            .filter(|m| config.modifiers.iter().any(|c| c == *m))
+            .cloned()
+            .collect();
+
+        self.rules.comparison = Comparison::from_names(&config.ignore)
+            .ok_or_else(|| format!("unknown difference to ignore: {}", config.ignore.join(",")))?;
+        self.seed = seed;
+        self.game_text = text.to_string();
+        // switching modes above made up a text of its own, and the quote or
+        // lesson it picked has nothing to do with this one
+        self.current_quote = match self.mode() {
+            Mode::Quote => self.quotes.iter().position(|q| q.text == text),
+            _ => None
+        };
+        self.lesson = None;
+        self.clock = self.start_clock();
+        Ok(())
+    }
+
+    pub fn mode(&self) -> Mode {
+        match self.mode_options[self.selected_mode_tab] {
+            "words" => Mode::Words,
+            "quote" => Mode::Quote,
+            "code" => Mode::Code,
+            "custom" => Mode::Custom,
+            "train" => Mode::Train,
+            "drill" => Mode::Drill,
+            "daily" => Mode::Daily,
+            _ => Mode::Time
+        }
+    }
+
+    fn mode_game_options(&self) -> Vec<&'static str> {
+        match self.mode() {
+            Mode::Time | Mode::Words | Mode::Train => vec!["100", "1k", "5k", "10k"],
+            Mode::Quote => vec!["short", "medium", "long", "thicc"],
+            Mode::Code => vec!["rust", "python", "js"],
+            Mode::Custom => vec!["text"],
+            Mode::Drill => vec!["due", "all"],
+            Mode::Daily => vec!["today"]
+        }
+    }
+
+    // the right hand tabs pick the test length: seconds in time mode and
+    // words in word count mode, quotes and code just end with the text
+    fn mode_timer_options(&self) -> Vec<&'static str> {
+        match self.mode() {
+            Mode::Words => vec!["10", "25", "50", "100"],
+            Mode::Daily => vec!["60"],
+            Mode::Time | Mode::Quote | Mode::Code | Mode::Custom | Mode::Train | Mode::Drill => vec!["30", "60", "120"]
+        }
+    }
+
+    fn mode_modifier_options(&self) -> Vec<&'static str> {
+        match self.mode() {
+            Mode::Time | Mode::Words | Mode::Quote | Mode::Custom | Mode::Train | Mode::Drill | Mode::Daily => vec!["punct", "nums"],
+            Mode::Code => vec!["indent"]
+        }
+    }
+
+    fn on_mode_change(&mut self) {
+        self.game_options = self.mode_game_options();
+        self.timer_options = self.mode_timer_options();
+        self.modifier_options = self.mode_modifier_options();
+        self.selected_game_tab = 0;
+        self.selected_timer_tab = 0;
+        self.selected_modifier_tab = 0;
+        self.game_text = self.gen_test();
+        self.clock = self.start_clock();
+    }
+
+    pub fn start_game<B: Backend> (&mut self, term: &mut Terminal<B>) -> io::Result<()> {
+        // a drill with nothing to drill has no text to type
+        if self.game_text.is_empty() {
+            return Ok(());
+        }
+
+        self.ghost = self.load_ghost();
+        self.session = TypingSession::new(&self.game_text, self.rules)
+            .skip_indentation(self.skips_indentation())
+            .word_target(self.word_target());
+
+        term.clear()?;
+        self.clock = self.start_clock();
         self.state = GameState::During;
+        Ok(())
+    }
+
+    /// Ends a test that ran its course, when the time, the text or the word
+    /// target runs out.
+    pub fn finish_game(&mut self) {
+        self.close_game(true);
+    }
+
+    /// Ends the test early from enter. The result is still shown but never
+    /// saved, so a few quick letters can't become a personal best.
+    pub fn abort_game(&mut self) {
+        self.close_game(false);
+    }
+
+    fn close_game(&mut self, completed: bool) {
+        let elapsed = self.timer.take().map(|t| t.elapsed()).unwrap_or_default();
+        self.session.finish(elapsed);
+
+        let session = &self.session;
+        let (result, elapsed) = match (session.result(), session.elapsed()) {
+            (Some(result), Some(elapsed)) => (result, elapsed),
+            _ => return
+        };
+        // every test gets a replay, even one that ended early or failed, but
+        // only the ones that ran their course go into the history. the
+        // result is still shown if the files can't be written
+        if !self.replaying {
+            let rules = session.rules();
+            let replay = Replay::new(self.test_config(), self.seed, &self.game_text, session.keystrokes(), elapsed)
+                .rules(rules.stop_on, rules.difficulty, rules.lock_correct)
+                .save()
+                .ok();
+            if completed && !session.failed() && !elapsed.is_zero() {
+                let record = Record::new(self.test_config(), self.seed, result.wpm, result.accuracy, replay);
+                let _ = self.history.add(record);
+            }
+
+            // keys and words from a failed test are still practice
+            let _ = self.key_history.add(session.keystrokes());
+            let typed = typed_words(&self.game_text, session.typed(), session.rules().comparison, session.keystrokes());
+            let _ = self.problem_words.update(&typed);
+        }
+
+        self.result = Some(result);
+        self.state = GameState::Post;
     }
 
     pub fn end_game(&mut self) {
-        self.my_game_text = "".to_string();
+        self.session = TypingSession::default();
         self.state = GameState::Pre;
         self.game_text = self.gen_test();
-        self.cursor_index = 0;
+        self.timer = None;
+        self.clock = self.start_clock();
+        self.replaying = false;
+        self.ghost = None;
     }
 
-    pub fn on_char(&mut self, c: char) {
-        if c == '\x08' {
-            if !self.my_game_text.is_empty() {
-                let chars: Vec<char> = self.my_game_text.chars().collect();
-                if chars[self.cursor_index - 1] == '\0' {
-                    while self.cursor_index > 0 && chars[self.cursor_index - 1] == '\0' {
-                        self.my_game_text.pop();
-                        self.cursor_index -= 1;
-                    }
-                } else {
-                    self.my_game_text.pop();
-                    self.cursor_index -= 1;
-                }
+    /// Sends everyone in the race the current text and starts the countdown.
+    pub fn start_race(&mut self) {
+        let config = self.test_config();
+        if let Some(race) = &mut self.race {
+            if self.game_text.is_empty() {
+                return;
             }
-        } else if c == ' ' {
-            let chars: Vec<char> = self.game_text.chars().collect();
+            race.start(&config, self.seed, &self.game_text);
+        }
+    }
 
-            if chars[self.cursor_index] != ' ' {
-                if self.cursor_index == 0 || chars[self.cursor_index - 1] == ' ' {
-                    return;
+    pub fn on_race_event(&mut self, event: RaceEvent) {
+        match event {
+            RaceEvent::Test(config, seed, text) => {
+                // a new race pulls everyone off the results screen
+                if !matches!(self.state, GameState::Pre) {
+                    self.end_game();
                 }
+                let _ = self.load_test(&config, seed, &text);
+            },
+            event => if let Some(race) = &mut self.race {
+                race.on_event(event);
+            }
+        }
+    }
 
-                while chars[self.cursor_index + 1] != ' ' {
-                    self.my_game_text.push('\0');
-                    self.cursor_index += 1;
+    /// Whether the race countdown just ran out and the test should start.
+    pub fn race_ready(&mut self) -> bool {
+        match &mut self.race {
+            Some(race) => race.ready() && matches!(self.state, GameState::Pre),
+            None => false
+        }
+    }
+
+    /// Shares how far this player has got with the rest of the race.
+    pub fn update_race(&mut self) {
+        if matches!(self.state, GameState::Pre) {
+            return;
+        }
+        let progress = self.session.progress();
+        if let Some(race) = &mut self.race {
+            race.update(progress);
+        }
+    }
+
+    // the personal best only makes a ghost when it was typed on the same text
+    fn load_ghost(&self) -> Option<Ghost> {
+        match self.ghost_source.as_ref()? {
+            GhostSource::Pace(wpm) => Some(Ghost::Pace(*wpm)),
+            GhostSource::PersonalBest => {
+                let config = self.test_config();
+                let best = self.history.records
+                    .iter()
+                    .filter(|r| r.config == config && r.seed == self.seed && r.replay.is_some())
+                    .max_by(|a, b| a.wpm.total_cmp(&b.wpm))?;
+                let replay = Replay::load_saved(best.replay.as_ref()?).ok()?;
+
+                if replay.text == self.game_text {
+                    Some(Ghost::from_replay(&replay))
+                } else {
+                    None
                 }
-                self.my_game_text.push('\0'); // last character on word
-                self.my_game_text.push('\0'); // space fill
-                self.cursor_index += 2;
-            } else {
-                self.my_game_text.push(' ');
-                self.cursor_index += 1;
             }
-        } else {
-            self.my_game_text.push(c);
-            self.cursor_index += 1;
         }
     }
 
+    /// Where the ghost caret is, once the test is under way.
+    pub fn ghost_position(&self) -> Option<usize> {
+        let elapsed = self.timer.as_ref()?.elapsed();
+        self.ghost.as_ref().map(|g| g.position(elapsed))
+    }
+
+    pub fn toggle_heatmap(&mut self) {
+        self.heatmap = match self.heatmap {
+            Heatmap::Errors => Heatmap::Latency,
+            Heatmap::Latency => Heatmap::Errors
+        };
+    }
+
+    pub fn timer_seconds(&self) -> u16 {
+        self.timer_options[self.selected_timer_tab].parse().unwrap()
+    }
+
+    /// Length of the countdown, or `None` when the test ends with the text.
+    pub fn countdown(&self) -> Option<u16> {
+        match self.mode() {
+            Mode::Time | Mode::Train | Mode::Daily => Some(self.timer_seconds()),
+            Mode::Words | Mode::Quote | Mode::Code | Mode::Custom | Mode::Drill => None
+        }
+    }
+
+    /// Number of correct words that ends a word count test.
+    pub fn word_target(&self) -> Option<usize> {
+        match self.mode() {
+            Mode::Words => self.timer_options[self.selected_timer_tab].parse().ok(),
+            Mode::Time | Mode::Quote | Mode::Code | Mode::Custom | Mode::Train | Mode::Drill | Mode::Daily => None
+        }
+    }
+
+    fn start_clock(&self) -> u16 {
+        self.countdown().unwrap_or(0)
+    }
+
+    pub fn on_timer_update(&mut self, clock: u16) {
+        if self.timer.is_none() {
+            return;
+        }
+
+        self.clock = clock;
+        if clock == 0 && self.countdown().is_some() {
+            self.finish_game();
+        }
+    }
+
+    pub fn on_char(&mut self, c: char) {
+        let key = match c {
+            '\x08' => Key::Backspace,
+            c => Key::Char(c)
+        };
+        self.feed(key);
+    }
+
+    pub fn delete_word(&mut self) {
+        self.feed(Key::DeleteWord);
+    }
+
+    // keys are timed by the test clock, so a replay played back faster still
+    // records the pace it was typed at
+    fn feed(&mut self, key: Key) {
+        let at = self.timer.as_ref().map(Timer::elapsed).unwrap_or_default();
+        self.session.feed(key, at);
+        if self.session.is_finished() {
+            self.finish_game();
+        }
+    }
+
+    /// Whether leading indentation is filled in when a new line is reached.
+    pub fn skips_indentation(&self) -> bool {
+        matches!(self.mode(), Mode::Code) && !self.modifier_enabled("indent")
+    }
+
+    pub fn test_config(&self) -> TestConfig {
+        let timer_option = match self.mode() {
+            Mode::Time | Mode::Words | Mode::Train | Mode::Daily => self.timer_options[self.selected_timer_tab],
+            Mode::Quote | Mode::Code | Mode::Custom | Mode::Drill => "-"
+        };
+
+        // custom text is compared against other runs of the same text, and
+        // the daily test against other runs on the same day
+        let game_option = match (self.mode(), &self.custom_text) {
+            (Mode::Custom, Some(custom)) => custom.name.clone(),
+            (Mode::Daily, _) => daily::date(daily::today()),
+            _ => self.game_options[self.selected_game_tab].to_string()
+        };
+
+        // the daily test always uses the built in list so everyone types
+        // the same words
+        let word_list = match self.mode() {
+            Mode::Daily => &self.word_lists[0],
+            _ => &self.word_lists[self.selected_word_list]
+        };
+
+        // quotes and custom text are typed as written, and modifiers are kept
+        // in option order so configs compare equal
+        let modifiers: Vec<&str> = match self.mode() {
+            Mode::Quote | Mode::Custom | Mode::Drill | Mode::Daily => vec![],
+            Mode::Time | Mode::Words | Mode::Code | Mode::Train => self.modifier_options
+                .iter()
+                .filter(|m| self.modifier_enabled(m))
+                .cloned()
+                .collect()
+        };
+
+        TestConfig {
+            mode: self.mode_options[self.selected_mode_tab].to_string(),
+            word_list: word_list.name.clone(),
+            game_option,
+            timer_option: timer_option.to_string(),
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+            ignore: self.rules.comparison.names(),
+        }
+    }
+
+    pub fn quote(&self) -> Option<&Quote> {
+        self.current_quote.map(|i| &self.quotes[i])
+    }
+
+    pub fn personal_best(&self) -> Option<&Record> {
+        self.history.best(&self.test_config())
+    }
+
     pub fn gen_test(&mut self) -> String {
-        let opt = self.game_options[self.selected_game_tab].to_string().replace('k', "000");
-        let mut r: usize = opt.parse().unwrap();
-        if r > self.words.len() {
-            r = self.words.len();
+        self.seed = match (self.mode(), self.fixed_seed) {
+            (Mode::Daily, _) => daily::today(),
+            (_, Some(seed)) => seed,
+            (_, None) => rand::random()
+        };
+        // a named generator, so a seed gives the same text on every platform
+        // and rand version
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+
+        self.current_quote = None;
+        self.lesson = None;
+        match self.mode() {
+            Mode::Quote => return self.gen_quote(&mut rng),
+            Mode::Code => return self.gen_snippet(&mut rng),
+            Mode::Custom => return self.custom_text.as_ref().map(|c| c.text.clone()).unwrap_or_default(),
+            Mode::Drill => return self.gen_drill(&mut rng),
+            Mode::Time | Mode::Words | Mode::Train | Mode::Daily => {}
         }
 
-        let mut out = "".to_string();
-        let mut last_word = "";
-        for _ in 0..500 {
-            let mut rng = rand::thread_rng();
-            let rand = rng.gen_range(0..=r);
+        let (words, mut r) = match self.mode() {
+            Mode::Daily => (&self.word_lists[0].words, DAILY_WORDS),
+            _ => {
+                let opt = self.game_options[self.selected_game_tab].to_string().replace('k', "000");
+                (&self.word_lists[self.selected_word_list].words, opt.parse().unwrap())
+            }
+        };
+        if r > words.len() {
+            r = words.len();
+        }
+
+        let mut out: Vec<String> = vec![];
+        if let Mode::Train = self.mode() {
+            let lesson = self.key_history.lesson();
+            out = lesson.pick_words(words, r, 500, &mut rng);
+            self.lesson = Some(lesson);
+        } else {
+            let mut last_word = "";
+            for _ in 0..500 {
+                let rand = rng.gen_range(0..r);
+
+                if words[rand] == last_word {
+                    continue;
+                }
 
-            if self.words[rand] == last_word {
-                continue;
+                out.push(words[rand].clone());
+                last_word = &words[rand];
             }
+        }
+
+        // the daily test is the same for everyone, modifiers and all
+        if let Mode::Daily = self.mode() {
+            return out.join(" ");
+        }
 
-            out.push_str(self.words[rand]);
-            out.push(' ');
-            last_word = self.words[rand];
+        if self.modifier_enabled("nums") {
+            add_numbers(&mut out, &mut rng);
         }
+        if self.modifier_enabled("punct") {
+            add_punctuation(&mut out, &mut rng);
+        }
+
+        out.join(" ")
+    }
+
+    // each word to drill comes up a few times, in a shuffled order
+    fn gen_drill(&mut self, rng: &mut ChaCha8Rng) -> String {
+        let due_only = self.game_options[self.selected_game_tab] == "due";
+        let mut out: Vec<&str> = vec![];
+        for w in self.problem_words.drill(due_only).iter().take(DRILL_WORDS) {
+            for _ in 0..DRILL_REPEATS {
+                out.push(&w.word);
+            }
+        }
+
+        out.shuffle(rng);
+        out.join(" ")
+    }
 
-        out = out.trim().to_string();
-        out
+    fn gen_snippet(&mut self, rng: &mut ChaCha8Rng) -> String {
+        let language = self.game_options[self.selected_game_tab];
+        let matching: Vec<&Snippet> = self.snippets
+            .iter()
+            .filter(|s| s.language == language)
+            .collect();
+
+        // every language has bundled snippets, so there is always one to pick
+        matching[rng.gen_range(0..matching.len())].text.clone()
     }
+
+    fn gen_quote(&mut self, rng: &mut ChaCha8Rng) -> String {
+        let length = self.game_options[self.selected_game_tab];
+        let matching: Vec<usize> = (0..self.quotes.len())
+            .filter(|&i| self.quotes[i].has_length(length))
+            .collect();
+
+        // fall back to any quote if nothing has the chosen length
+        self.current_quote = if matching.is_empty() {
+            Some(rng.gen_range(0..self.quotes.len()))
+        } else {
+            Some(matching[rng.gen_range(0..matching.len())])
+        };
+
+        self.quotes[self.current_quote.unwrap()].text.clone()
+    }
+}
+
+pub enum Mode {
+    Time,
+    Words,
+    Quote,
+    Code,
+    Custom,
+    Train,
+    Drill,
+    Daily
+}
+
+/// What the keyboard on the results screen is colored by.
+#[derive(Clone, Copy)]
+pub enum Heatmap {
+    Errors,
+    Latency
 }
 
-#[allow(dead_code)]
 pub enum GameState {
     Pre,
     During,
@@ -191,7 +795,10 @@ pub enum GameState {
 }
 
 pub enum FocusedWindow {
+   ModeOptions,
    GameOptions,
+   WordListOptions,
+   ModifierOptions,
    TimerOptions,
    Game
 }
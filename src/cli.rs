@@ -0,0 +1,163 @@
+use std::{env, path::PathBuf};
+
+use rype_core::{Difficulty, StopOn};
+use rype_core::text::Comparison;
+
+/// Where custom text to practice comes from.
+pub enum TextSource {
+    File(PathBuf),
+    Stdin,
+}
+
+/// What the ghost caret races at.
+pub enum GhostSource {
+    /// The personal best on the same text, when it was saved with a replay.
+    PersonalBest,
+    /// A steady speed in words per minute.
+    Pace(f64),
+}
+
+/// Command line options.
+pub struct Args {
+    pub wordlist: Option<PathBuf>,
+    pub text: Option<TextSource>,
+    pub lowercase: bool,
+    pub ascii: bool,
+    pub lines: Option<usize>,
+    pub seed: Option<u64>,
+    /// Replay file to play back instead of practising.
+    pub replay: Option<PathBuf>,
+    pub speed: f64,
+    pub ghost: Option<GhostSource>,
+    /// Host a race on `port`.
+    pub host: bool,
+    pub port: u16,
+    /// Address of a race to join.
+    pub join: Option<String>,
+    pub name: Option<String>,
+    pub stop_on: Option<StopOn>,
+    pub difficulty: Difficulty,
+    pub lock_correct: bool,
+    pub comparison: Comparison,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Args {
+            wordlist: None,
+            text: None,
+            lowercase: false,
+            ascii: false,
+            lines: None,
+            seed: None,
+            replay: None,
+            speed: 1.0,
+            ghost: None,
+            host: false,
+            port: 7878,
+            join: None,
+            name: None,
+            stop_on: None,
+            difficulty: Difficulty::Normal,
+            lock_correct: false,
+            comparison: Comparison::default(),
+        };
+
+        let mut argv = env::args().skip(1);
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "--wordlist" => {
+                    let path = argv.next().ok_or("--wordlist needs a path")?;
+                    args.wordlist = Some(PathBuf::from(path));
+                },
+                "--text" => {
+                    let path = argv.next().ok_or("--text needs a path")?;
+                    args.text = Some(TextSource::File(PathBuf::from(path)));
+                },
+                "-" => args.text = Some(TextSource::Stdin),
+                "--lowercase" => args.lowercase = true,
+                "--ascii" => args.ascii = true,
+                "--lines" => {
+                    let lines = argv.next().ok_or("--lines needs a number")?;
+                    match lines.parse() {
+                        Ok(n) if n > 0 => args.lines = Some(n),
+                        _ => return Err(format!("--lines needs a number above zero, got {}", lines))
+                    }
+                },
+                "--seed" => {
+                    let seed = argv.next().ok_or("--seed needs a number")?;
+                    match seed.parse() {
+                        Ok(n) => args.seed = Some(n),
+                        _ => return Err(format!("--seed needs a number, got {}", seed))
+                    }
+                },
+                "replay" => {
+                    let path = argv.next().ok_or("replay needs a path")?;
+                    args.replay = Some(PathBuf::from(path));
+                },
+                "--speed" => {
+                    let speed = argv.next().ok_or("--speed needs a number")?;
+                    match speed.parse() {
+                        Ok(n) if n > 0.0 => args.speed = n,
+                        _ => return Err(format!("--speed needs a number above zero, got {}", speed))
+                    }
+                },
+                "--ghost" => {
+                    let ghost = argv.next().ok_or("--ghost needs pb or a wpm")?;
+                    args.ghost = match ghost.as_str() {
+                        "pb" => Some(GhostSource::PersonalBest),
+                        _ => match ghost.parse() {
+                            Ok(n) if n > 0.0 => Some(GhostSource::Pace(n)),
+                            _ => return Err(format!("--ghost needs pb or a wpm above zero, got {}", ghost))
+                        }
+                    };
+                },
+                "host" => args.host = true,
+                "join" => {
+                    let addr = argv.next().ok_or("join needs an address")?;
+                    args.join = Some(addr);
+                },
+                "--port" => {
+                    let port = argv.next().ok_or("--port needs a number")?;
+                    match port.parse() {
+                        Ok(n) => args.port = n,
+                        _ => return Err(format!("--port needs a port number, got {}", port))
+                    }
+                },
+                "--name" => {
+                    let name = argv.next().ok_or("--name needs a name")?;
+                    if name.is_empty() || name.contains('\t') {
+                        return Err("--name can't be empty or have tabs".to_string());
+                    }
+                    args.name = Some(name);
+                },
+                "--stop-on" => {
+                    let stop_on = argv.next().ok_or("--stop-on needs letter or word")?;
+                    match StopOn::from_name(&stop_on) {
+                        Some(s) => args.stop_on = Some(s),
+                        None => return Err(format!("--stop-on needs letter or word, got {}", stop_on))
+                    }
+                },
+                "--difficulty" => {
+                    let difficulty = argv.next().ok_or("--difficulty needs normal, expert or master")?;
+                    match Difficulty::from_name(&difficulty) {
+                        Some(d) => args.difficulty = d,
+                        None => return Err(format!("--difficulty needs normal, expert or master, got {}", difficulty))
+                    }
+                },
+                "--lock-correct" => args.lock_correct = true,
+                "--ignore" => {
+                    let ignore = argv.next().ok_or("--ignore needs accents, case or quotes")?;
+                    let names: Vec<String> = ignore.split(',').map(str::to_string).collect();
+                    match Comparison::from_names(&names) {
+                        Some(c) => args.comparison = c,
+                        None => return Err(format!("--ignore needs a list of accents, case or quotes, got {}", ignore))
+                    }
+                },
+                _ => return Err(format!("unknown argument: {}", arg))
+            }
+        }
+
+        Ok(args)
+    }
+}
@@ -0,0 +1,55 @@
+use std::{
+    fs,
+    io::{self, Read}
+};
+
+use super::cli::TextSource;
+
+/// Text given on the command line to practice instead of generated tests.
+pub struct CustomText {
+    pub name: String,
+    pub text: String,
+}
+
+impl CustomText {
+    pub fn load(source: &TextSource, lowercase: bool, ascii: bool) -> io::Result<Self> {
+        let (name, raw) = match source {
+            TextSource::File(path) => {
+                let name = path
+                    .file_name()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "text".to_string());
+                let raw = fs::read_to_string(path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                (name, raw)
+            },
+            TextSource::Stdin => {
+                let mut raw = String::new();
+                io::stdin().read_to_string(&mut raw)?;
+                ("stdin".to_string(), raw)
+            }
+        };
+
+        let text = normalize(&raw, lowercase, ascii);
+        if text.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} has no text to type", name)));
+        }
+
+        Ok(CustomText { name, text })
+    }
+}
+
+/// Collapses all whitespace, including newlines, into single spaces and drops
+/// control characters, which can't be typed.
+pub fn normalize(text: &str, lowercase: bool, ascii: bool) -> String {
+    let mut text = text.to_string();
+    text.retain(|c| !c.is_control() || c.is_whitespace());
+    if ascii {
+        text.retain(|c| c.is_ascii());
+    }
+    if lowercase {
+        text = text.to_lowercase();
+    }
+
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
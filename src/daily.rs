@@ -0,0 +1,47 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Days since the unix epoch in local time, which doubles as the seed for
+/// the daily test so everyone gets the same text on the same day.
+pub fn today() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    (now as i64 + utc_offset(now)).max(0) as u64 / 86400
+}
+
+/// A day from `today` as `yyyy-mm-dd`.
+pub fn date(days: u64) -> String {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// seconds east of UTC for the local timezone, or zero if it can't be found
+#[cfg(unix)]
+fn utc_offset(time: u64) -> i64 {
+    let time = time as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::localtime_r(&time, &mut tm) };
+    if result.is_null() {
+        0
+    } else {
+        tm.tm_gmtoff as i64
+    }
+}
+
+// elsewhere the day turns over at midnight UTC
+#[cfg(not(unix))]
+fn utc_offset(_time: u64) -> i64 {
+    0
+}
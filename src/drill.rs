@@ -0,0 +1,166 @@
+use std::{
+    io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use rype_core::stats::Keystroke;
+use rype_core::text::{graphemes, Comparison};
+
+use super::paths::{data_dir, read_data_file, write_data_file};
+
+// seconds to wait before a word comes back, after one, two and three clean
+// typings in a row
+const INTERVALS: [u64; 3] = [10 * 60, 60 * 60, 24 * 60 * 60];
+// clean typings in a row that take a word off the list
+const CLEAN_TYPINGS: usize = 4;
+
+/// A word that has been mistyped or skipped.
+pub struct ProblemWord {
+    pub word: String,
+    pub misses: usize,
+    /// Clean typings since the last miss.
+    pub streak: usize,
+    /// Unix time the word is next due for practice.
+    pub due: u64,
+}
+
+impl ProblemWord {
+    pub fn is_due(&self, now: u64) -> bool {
+        self.due <= now
+    }
+
+    // one word per line, fields separated by tabs
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\n", self.word, self.misses, self.streak, self.due)
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        Some(ProblemWord {
+            word: fields.next()?.to_string(),
+            misses: fields.next()?.parse().ok()?,
+            streak: fields.next()?.parse().ok()?,
+            due: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// Every word still being drilled, backed by `$XDG_DATA_HOME/rype/words`.
+pub struct ProblemWords {
+    path: Option<PathBuf>,
+    pub words: Vec<ProblemWord>,
+}
+
+impl ProblemWords {
+    /// Reads the words missed in earlier tests.
+    pub fn load() -> io::Result<Self> {
+        let path = data_dir().map(|d| d.join("words"));
+        let words = read_data_file(path.as_deref())?
+            .lines()
+            .filter_map(ProblemWord::from_line)
+            .collect();
+
+        Ok(ProblemWords { path, words })
+    }
+
+    /// Adds missed words to the list and moves due words along their
+    /// schedule, then rewrites the file.
+    ///
+    /// A miss puts a word back at the start of its schedule. A clean typing
+    /// only counts while the word is due, so typing it again straight away
+    /// doesn't rush it off the list.
+    pub fn update(&mut self, typed: &[(String, bool)]) -> io::Result<()> {
+        let now = now();
+
+        for (word, clean) in typed {
+            let i = self.words.iter().position(|w| &w.word == word);
+            match (i, clean) {
+                (Some(i), false) => {
+                    let w = &mut self.words[i];
+                    w.misses += 1;
+                    w.streak = 0;
+                    w.due = now;
+                },
+                (None, false) => self.words.push(ProblemWord {
+                    word: word.clone(),
+                    misses: 1,
+                    streak: 0,
+                    due: now,
+                }),
+                (Some(i), true) if self.words[i].is_due(now) => {
+                    let w = &mut self.words[i];
+                    w.streak += 1;
+                    if w.streak >= CLEAN_TYPINGS {
+                        self.words.remove(i);
+                    } else {
+                        w.due = now + INTERVALS[w.streak - 1];
+                    }
+                },
+                (_, true) => {}
+            }
+        }
+
+        let contents: String = self.words.iter().map(ProblemWord::to_line).collect();
+        write_data_file(self.path.as_deref(), &contents)
+    }
+
+    /// Words to drill, most missed first.
+    pub fn drill(&self, due_only: bool) -> Vec<&ProblemWord> {
+        let now = now();
+        let mut words: Vec<&ProblemWord> = self.words
+            .iter()
+            .filter(|w| !due_only || w.is_due(now))
+            .collect();
+        words.sort_by(|a, b| b.misses.cmp(&a.misses).then(a.due.cmp(&b.due)));
+        words
+    }
+}
+
+/// Words of `game_text` the caret got all the way through, and whether each
+/// was typed without a single wrong key, skip or extra letter, even one fixed
+/// later.
+pub fn typed_words(game_text: &str, my_game_text: &str, comparison: Comparison, keystrokes: &[Keystroke]) -> Vec<(String, bool)> {
+    let target = graphemes(game_text);
+    let typed = graphemes(my_game_text);
+    let mut words = vec![];
+
+    let mut start = 0;
+    while start < typed.len() {
+        if target[start].trim().is_empty() {
+            start += 1;
+            continue;
+        }
+
+        let end = (start..target.len())
+            .find(|&i| target[i].trim().is_empty())
+            .unwrap_or(target.len());
+        if end > typed.len() {
+            break;
+        }
+
+        // extra letters are typed with the caret on the space after the word
+        let missed = (start..end).any(|i| !comparison.matches(typed[i], target[i]))
+            || keystrokes
+                .iter()
+                .any(|k| k.index >= start && k.index <= end && k.actual != '\x08' && k.actual != k.expected);
+
+        // punctuation is part of the word typed, but not of the word drilled
+        let word = target[start..end].concat();
+        let word = word.trim_matches(|c: char| c.is_ascii_punctuation());
+        if !word.is_empty() {
+            words.push((word.to_string(), !missed));
+        }
+
+        start = end;
+    }
+
+    words
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
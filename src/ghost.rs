@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use super::replay::Replay;
+
+/// A second caret to race against, moving through the text at the pace of an
+/// earlier run or at a steady speed.
+pub enum Ghost {
+    /// Caret positions over time, from a replay of the same text.
+    Replay(Vec<(Duration, usize)>),
+    /// Words per minute.
+    Pace(f64),
+}
+
+impl Ghost {
+    pub fn from_replay(replay: &Replay) -> Self {
+        // each key leaves the caret where the next key finds it
+        let mut positions: Vec<(Duration, usize)> = replay.keys
+            .windows(2)
+            .map(|w| (w[0].at, w[1].index))
+            .collect();
+        if let Some(last) = replay.keys.last() {
+            positions.push((last.at, last.index + 1));
+        }
+
+        Ghost::Replay(positions)
+    }
+
+    /// Where the ghost's caret is `elapsed` into the test.
+    pub fn position(&self, elapsed: Duration) -> usize {
+        match self {
+            Ghost::Replay(positions) => {
+                let i = positions.partition_point(|(at, _)| *at <= elapsed);
+                if i == 0 {
+                    0
+                } else {
+                    positions[i - 1].1
+                }
+            },
+            // a word is five characters
+            Ghost::Pace(wpm) => (elapsed.as_secs_f64() / 60.0 * wpm * 5.0) as usize
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use super::paths::{append_data_file, data_dir, read_data_file};
+
+/// Everything that has to match for two results to be comparable.
+#[derive(Clone, PartialEq)]
+pub struct TestConfig {
+    pub mode: String,
+    pub word_list: String,
+    pub game_option: String,
+    pub timer_option: String,
+    pub modifiers: Vec<String>,
+    /// Differences between typed and expected text that were let go.
+    pub ignore: Vec<String>,
+}
+
+/// One finished test as stored in the history file.
+pub struct Record {
+    pub timestamp: u64,
+    pub config: TestConfig,
+    pub seed: u64,
+    pub wpm: f64,
+    pub accuracy: f64,
+    /// File name of the test's replay, if one was saved.
+    pub replay: Option<String>,
+}
+
+impl Record {
+    pub fn new(config: TestConfig, seed: u64, wpm: f64, accuracy: f64, replay: Option<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Record {
+            timestamp,
+            config,
+            seed,
+            wpm,
+            accuracy,
+            replay,
+        }
+    }
+
+    // a line per test, tab separated, with newer fields appended at the end
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{:.2}\t{:.2}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp,
+            self.config.game_option,
+            self.config.timer_option,
+            self.seed,
+            self.wpm,
+            self.accuracy,
+            self.config.word_list,
+            self.config.mode,
+            self.config.modifiers.join(","),
+            self.replay.as_deref().unwrap_or(""),
+            self.config.ignore.join(",")
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let timestamp = fields.next()?.parse().ok()?;
+        let game_option = fields.next()?.to_string();
+        let timer_option = fields.next()?.to_string();
+        let seed = fields.next()?.parse().ok()?;
+        let wpm = fields.next()?.parse().ok()?;
+        let accuracy = fields.next()?.parse().ok()?;
+
+        // fields added later are optional so older files still load
+        let word_list = fields.next().unwrap_or("english").to_string();
+        let mode = fields.next().unwrap_or("time").to_string();
+        let modifiers = split_list(fields.next().unwrap_or(""));
+        let replay = fields.next().filter(|r| !r.is_empty()).map(str::to_string);
+        let config = TestConfig {
+            mode,
+            word_list,
+            game_option,
+            timer_option,
+            modifiers,
+            ignore: split_list(fields.next().unwrap_or("")),
+        };
+
+        Some(Record {
+            timestamp,
+            config,
+            seed,
+            wpm,
+            accuracy,
+            replay,
+        })
+    }
+}
+
+/// Every finished test, backed by `$XDG_DATA_HOME/rype/history`.
+pub struct History {
+    path: Option<PathBuf>,
+    pub records: Vec<Record>,
+}
+
+impl History {
+    /// Reads every past test from the data directory.
+    pub fn load() -> io::Result<Self> {
+        let path = data_dir().map(|d| d.join("history"));
+        let records = read_data_file(path.as_deref())?
+            .lines()
+            .filter_map(Record::from_line)
+            .collect();
+
+        Ok(History { path, records })
+    }
+
+    pub fn add(&mut self, record: Record) -> io::Result<()> {
+        let line = record.to_line();
+        self.records.push(record);
+        append_data_file(self.path.as_deref(), &line)
+    }
+
+    pub fn best(&self, config: &TestConfig) -> Option<&Record> {
+        self.records
+            .iter()
+            .filter(|r| &r.config == config)
+            .max_by(|a, b| a.wpm.total_cmp(&b.wpm))
+    }
+}
+
+/// Splits a comma separated field, as modifiers and ignored differences are
+/// stored.
+pub fn split_list(field: &str) -> Vec<String> {
+    field
+        .split(',')
+        .filter(|m| !m.is_empty())
+        .map(str::to_string)
+        .collect()
+}
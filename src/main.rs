@@ -1,58 +1,158 @@
 use std::{
+    env,
     io,
     thread,
-    sync::mpsc,
+    sync::{
+        mpsc,
+        Arc,
+        atomic::{AtomicBool, Ordering}
+    },
     time::{Duration, Instant}
 };
 use tui::{
     Terminal,
     backend::CrosstermBackend
 };
-use crossterm::{
-    terminal::{enable_raw_mode, disable_raw_mode},
-    event::{self, Event as CEvent, KeyCode}
-};
+use crossterm::event::{self, Event as CEvent, KeyCode, KeyEvent, KeyModifiers};
 
 mod ui;
 mod app;
 mod widgets;
+mod timer;
+mod history;
+mod paths;
+mod wordlist;
+mod cli;
+mod quotes;
+mod modifiers;
+mod snippets;
+mod customtext;
+mod training;
+mod drill;
+mod daily;
+mod replay;
+mod ghost;
+mod race;
+mod screen;
 
 use app::App;
 use app::GameState;
 use app::FocusedWindow;
+use app::Mode;
+use timer::Timer;
+use history::History;
+use wordlist::{WordList, load_word_lists};
+use cli::Args;
+use quotes::load_quotes;
+use snippets::load_snippets;
+use customtext::CustomText;
+use training::KeyHistory;
+use drill::ProblemWords;
+use replay::Replay;
+use screen::ScreenGuard;
+use race::{Race, RaceEvent};
 
 pub enum Event<I> {
     Input(I),
     TimerUpdate(u16),
-    Tick
+    Tick,
+    /// A key played back from a replay.
+    Replay(KeyCode),
+    /// The replay has reached the time its test ended.
+    ReplayEnd,
+    Race(RaceEvent),
+    /// The process was told to stop, or the terminal went away.
+    Quit
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // check getting list of words
-    // this kills compile time - should chek if i can do anything about that
-    let word_list: Vec<&str> = include!("words.txt");
-    println!("{}", word_list.len());
+    let args = Args::parse()?;
+
+    let mut word_lists = load_word_lists()?;
+    let mut selected_word_list = 0;
+    if let Some(path) = &args.wordlist {
+        let list = WordList::from_file(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        word_lists.push(list);
+        selected_word_list = word_lists.len() - 1;
+    }
+
+    let quotes = load_quotes()?;
+    let snippets = load_snippets()?;
+    let history = History::load()?;
+    let key_history = KeyHistory::load()?;
+    let problem_words = ProblemWords::load()?;
+
+    let custom_text = match &args.text {
+        Some(source) => Some(CustomText::load(source, args.lowercase, args.ascii)?),
+        None => None
+    };
+
+    let replay = match &args.replay {
+        Some(path) => Some(Replay::load(path).map_err(|e| format!("{}: {}", path.display(), e))?),
+        None => None
+    };
+
+    // events from input, timers, replays and races all go through here
+    let (tx, rx) = mpsc::channel();
+
+    // connect before the terminal is taken over so errors print normally
+    let name = args.name.clone()
+        .or_else(|| env::var("USER").ok())
+        .unwrap_or_else(|| "player".to_string());
+    let race = if args.host {
+        Some(Race::host(args.port, name, tx.clone()).map_err(|e| format!("can't host on port {}: {}", args.port, e))?)
+    } else if let Some(addr) = &args.join {
+        Some(Race::join(addr, name, tx.clone()).map_err(|e| format!("can't join {}: {}", addr, e))?)
+    } else {
+        None
+    };
 
-    // basic setup
+    // the terminal is put back by the guard on every way out of main, and
+    // by the hook on a panic
+    screen::install_panic_hook();
+    let _screen = ScreenGuard::enter()?;
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
-    enable_raw_mode().expect("can run in raw mode");
+
+    quit_on_signals(tx.clone())?;
 
     // input setup
-    let (tx, rx) = mpsc::channel();
-    let tick_rate = Duration::from_millis(200);
-    thread::spawn(move || {
+    let timer_tx = tx.clone();
+    let replay_tx = tx.clone();
+    // fast enough for the ghost caret to move smoothly
+    let tick_rate = Duration::from_millis(50);
+    let input_running = Arc::new(AtomicBool::new(true));
+    let thread_running = Arc::clone(&input_running);
+    let input = thread::spawn(move || {
         let mut last_tick = Instant::now();
-        loop {
+        while thread_running.load(Ordering::SeqCst) {
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
 
-            if event::poll(timeout).expect("poll works") {
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
+            // a terminal that can't be read from is gone, so the app stops
+            let key = match event::poll(timeout) {
+                Ok(true) => match event::read() {
+                    Ok(CEvent::Key(key)) => Some(key),
+                    Ok(_) => None,
+                    Err(_) => {
+                        let _ = tx.send(Event::Quit);
+                        return;
+                    }
+                },
+                Ok(false) => None,
+                Err(_) => {
+                    let _ = tx.send(Event::Quit);
+                    return;
+                }
+            };
+
+            if let Some(key) = key {
+                if tx.send(Event::Input(key)).is_err() {
+                    return;
                 }
             }
 
@@ -62,7 +162,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    let mut app = App::new(word_list);
+    let mut app = App::new(word_lists, selected_word_list, quotes, snippets, history, key_history, problem_words);
+    if let Some(custom_text) = custom_text {
+        app.set_custom_text(custom_text);
+    }
+    if let Some(lines) = args.lines {
+        app.typing_lines = lines;
+    }
+    app.ghost_source = args.ghost;
+    app.rules.stop_on = args.stop_on;
+    app.rules.difficulty = args.difficulty;
+    app.rules.comparison = args.comparison;
+    app.rules.lock_correct = args.lock_correct;
+    if let Some(seed) = args.seed {
+        app.fixed_seed = Some(seed);
+        app.game_text = app.gen_test();
+    }
+    app.race = race;
+    if let Some(replay) = replay {
+        app.load_replay(&replay)?;
+        app.start_game(&mut terminal)?;
+        play_replay(replay, args.speed, replay_tx);
+    }
 
     // draw loop
     loop {
@@ -77,12 +198,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     KeyCode::Char('q') => {
                         app.should_quit = true;
                     }
+                    // players wait for the host to pick the text and start,
+                    // and once the countdown runs the text can't change
+                    _ if app.race.as_ref().is_some_and(|r| !r.is_host() || r.starts_at.is_some()) => {},
+                    KeyCode::Enter if app.race.is_some() => if let FocusedWindow::Game = app.focused_window {
+                        app.start_race();
+                    },
                     KeyCode::Tab => app.cycle_focus_forward(),
                     KeyCode::BackTab => app.cycle_focus_backward(),
                     KeyCode::Left => app.cycle_tab_backward(),
                     KeyCode::Right => app.cycle_tab_forward(),
+                    KeyCode::Char(' ') => app.toggle_modifier(),
                     KeyCode::Enter => if let FocusedWindow::Game = app.focused_window {
-                        app.start_game(&mut terminal);
+                        app.start_game(&mut terminal)?;
                     }
                     _ => {}
                 },
@@ -90,10 +218,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     KeyCode::Esc => {
                         app.should_quit = true;
                     },
-                    KeyCode::Char(c) => app.on_char(c),
-                    KeyCode::Backspace => app.on_char('\x08'),
-                    KeyCode::Enter => app.end_game(),
-                    _ => {}
+                    // a replay is watched, not typed along with
+                    _ if app.replaying => {},
+                    // abandons the test for a new one, nothing is saved
+                    KeyCode::Tab => app.end_game(),
+                    _ if deletes_word(&event) => app.delete_word(),
+                    code => type_key(&mut app, code, &timer_tx, 1.0)
                 },
                 GameState::Post => match event.code {
                     KeyCode::Esc => {
@@ -102,20 +232,117 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     KeyCode::Char('q') => {
                         app.should_quit = true;
                     }
-                    KeyCode::Enter => {},
+                    KeyCode::Enter => app.end_game(),
+                    KeyCode::Tab => app.toggle_heatmap(),
                     _ => {}
                 }
             },
+            Event::TimerUpdate(clock) => app.on_timer_update(clock),
             Event::Tick => {},
-            _ => {}
+            Event::Replay(code) => if let GameState::During = app.state {
+                type_key(&mut app, code, &timer_tx, args.speed);
+            },
+            Event::ReplayEnd => if let GameState::During = app.state {
+                app.finish_game();
+            },
+            Event::Race(event) => app.on_race_event(event),
+            Event::Quit => app.should_quit = true
+        }
+
+        // everyone starts typing together when the countdown runs out
+        if app.race_ready() {
+            app.start_game(&mut terminal)?;
+            app.timer = Some(Timer::start(timer_tx.clone(), app.countdown(), 1.0));
         }
+        app.update_race();
 
         if app.should_quit {
-            disable_raw_mode()?;
-            terminal.clear()?;
-            terminal.show_cursor()?;
             break;
         }
     }
+
+    input_running.store(false, Ordering::SeqCst);
+    let _ = input.join();
     Ok(())
 }
+
+// a key pressed during a test, typed or played back from a replay
+fn type_key(app: &mut App, code: KeyCode, timer_tx: &mpsc::Sender<Event<KeyEvent>>, speed: f64) {
+    match code {
+        KeyCode::Char(c) => {
+            // timer starts on the first keystroke
+            if app.timer.is_none() {
+                app.timer = Some(Timer::start(timer_tx.clone(), app.countdown(), speed));
+            }
+            app.on_char(c);
+        },
+        KeyCode::Backspace => app.on_char('\x08'),
+        KeyCode::Enter => match app.mode() {
+            Mode::Code => app.on_char('\n'),
+            _ => app.abort_game()
+        },
+        _ => {}
+    }
+}
+
+// ctrl+w, alt+backspace, and ctrl+backspace, which most terminals send as
+// ctrl+h
+fn deletes_word(event: &KeyEvent) -> bool {
+    match event.code {
+        KeyCode::Backspace => event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+        KeyCode::Char('w') | KeyCode::Char('h') => event.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false
+    }
+}
+
+// signals quit through the draw loop like esc does, so the terminal is
+// restored on the way out
+#[cfg(unix)]
+fn quit_on_signals(tx: mpsc::Sender<Event<KeyEvent>>) -> io::Result<()> {
+    use signal_hook::{
+        consts::{SIGHUP, SIGINT, SIGTERM},
+        iterator::Signals
+    };
+
+    let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT])?;
+    thread::spawn(move || {
+        if signals.forever().next().is_some() {
+            let _ = tx.send(Event::Quit);
+        }
+    });
+    Ok(())
+}
+
+// ctrl+c comes in as a key in raw mode, and closing the console window
+// ends the process without asking
+#[cfg(not(unix))]
+fn quit_on_signals(_tx: mpsc::Sender<Event<KeyEvent>>) -> io::Result<()> {
+    Ok(())
+}
+
+// sends the replay's keys at the times they were pressed, `speed` times faster
+fn play_replay(replay: Replay, speed: f64, tx: mpsc::Sender<Event<KeyEvent>>) {
+    thread::spawn(move || {
+        let start = Instant::now();
+        let wait_until = |at: Duration| {
+            if let Some(wait) = at.div_f64(speed).checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+        };
+
+        for k in replay.keys {
+            wait_until(k.at);
+            let code = match k.key {
+                '\x08' => KeyCode::Backspace,
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c)
+            };
+            if tx.send(Event::Replay(code)).is_err() {
+                return;
+            }
+        }
+
+        wait_until(replay.end);
+        let _ = tx.send(Event::ReplayEnd);
+    });
+}
@@ -0,0 +1,94 @@
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+const CONTRACTIONS: [(&str, &str); 20] = [
+    ("are", "aren't"),
+    ("can", "can't"),
+    ("could", "couldn't"),
+    ("did", "didn't"),
+    ("do", "don't"),
+    ("does", "doesn't"),
+    ("has", "hasn't"),
+    ("have", "haven't"),
+    ("is", "isn't"),
+    ("it", "it's"),
+    ("should", "shouldn't"),
+    ("that", "that's"),
+    ("there", "there's"),
+    ("they", "they're"),
+    ("was", "wasn't"),
+    ("we", "we're"),
+    ("were", "weren't"),
+    ("will", "won't"),
+    ("would", "wouldn't"),
+    ("you", "you're"),
+];
+
+/// Swaps some words for numbers of one to four digits.
+pub fn add_numbers(words: &mut [String], rng: &mut ChaCha8Rng) {
+    for word in words.iter_mut() {
+        if rng.gen_bool(0.1) {
+            let digits = rng.gen_range(1..=4);
+            *word = rng.gen_range(0..10u32.pow(digits)).to_string();
+        }
+    }
+}
+
+/// Turns a run of words into something closer to real sentences: contractions,
+/// quotes and parentheses, trailing punctuation, and a capital at the start of
+/// every sentence.
+pub fn add_punctuation(words: &mut [String], rng: &mut ChaCha8Rng) {
+    let mut sentence_start = true;
+    let last = words.len().saturating_sub(1);
+
+    for (i, word) in words.iter_mut().enumerate() {
+        if let Some((_, c)) = CONTRACTIONS.iter().find(|(w, _)| w == word) {
+            if rng.gen_bool(0.3) {
+                *word = c.to_string();
+            }
+        }
+
+        if sentence_start {
+            *word = capitalize(word);
+        }
+
+        let roll: f64 = rng.gen();
+        if roll < 0.04 {
+            *word = format!("\"{}\"", word);
+        } else if roll < 0.06 {
+            *word = format!("({})", word);
+        }
+
+        sentence_start = false;
+        if i == last {
+            word.push('.');
+            break;
+        }
+
+        let roll: f64 = rng.gen();
+        if roll < 0.05 {
+            word.push('.');
+            sentence_start = true;
+        } else if roll < 0.06 {
+            word.push('?');
+            sentence_start = true;
+        } else if roll < 0.065 {
+            word.push('!');
+            sentence_start = true;
+        } else if roll < 0.135 {
+            word.push(',');
+        } else if roll < 0.14 {
+            word.push(';');
+        } else if roll < 0.145 {
+            word.push(':');
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new()
+    }
+}
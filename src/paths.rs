@@ -0,0 +1,63 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf}
+};
+
+/// `$XDG_DATA_HOME/rype`, falling back to `~/.local/share/rype`.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local").join("share"),
+    };
+
+    Some(base.join("rype"))
+}
+
+/// Reads a file kept in the data directory, empty if it doesn't exist yet or
+/// there is no data directory.
+pub fn read_data_file(path: Option<&Path>) -> io::Result<String> {
+    let path = match path {
+        Some(p) => p,
+        None => return Ok(String::new())
+    };
+
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replaces a file in the data directory, creating the directory first.
+/// Without a data directory nothing is kept.
+pub fn write_data_file(path: Option<&Path>, contents: &str) -> io::Result<()> {
+    match path {
+        Some(p) => {
+            create_parent(p)?;
+            fs::write(p, contents)
+        },
+        None => Ok(())
+    }
+}
+
+/// Adds a line to the end of a file in the data directory, for files that
+/// only ever grow.
+pub fn append_data_file(path: Option<&Path>, line: &str) -> io::Result<()> {
+    match path {
+        Some(p) => {
+            create_parent(p)?;
+            let mut file = OpenOptions::new().create(true).append(true).open(p)?;
+            writeln!(file, "{}", line)
+        },
+        None => Ok(())
+    }
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use std::io;
+
+use super::paths::{data_dir, read_data_file};
+
+const DEFAULT_QUOTES: &str = include_str!("quotes.txt");
+
+/// A passage to type, with where it came from.
+pub struct Quote {
+    pub text: String,
+    pub author: String,
+    pub source: String,
+}
+
+impl Quote {
+    // quotes are stored one per line as `author<TAB>source<TAB>text`
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let author = fields.next()?.trim();
+        let source = fields.next()?.trim();
+        let text = fields.next()?.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        Some(Quote {
+            text: text.to_string(),
+            author: author.to_string(),
+            source: source.to_string(),
+        })
+    }
+
+    /// Whether the quote falls in a length category from the game options.
+    pub fn has_length(&self, length: &str) -> bool {
+        let len = self.text.chars().count();
+        match length {
+            "short" => len <= 100,
+            "medium" => len > 100 && len <= 300,
+            "long" => len > 300 && len <= 600,
+            _ => len > 600,
+        }
+    }
+}
+
+/// The bundled quotes followed by any in `<data dir>/quotes.txt`.
+pub fn load_quotes() -> io::Result<Vec<Quote>> {
+    let mut quotes: Vec<Quote> = DEFAULT_QUOTES.lines().filter_map(Quote::from_line).collect();
+
+    let path = data_dir().map(|d| d.join("quotes.txt"));
+    quotes.extend(read_data_file(path.as_deref())?.lines().filter_map(Quote::from_line));
+
+    Ok(quotes)
+}
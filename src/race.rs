@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{mpsc::{self, Sender}, Arc, Mutex},
+    thread,
+    time::{Duration, Instant}
+};
+
+use rype_core::Progress;
+
+use super::Event;
+use super::history::{split_list, TestConfig};
+use super::replay::{escape, unescape};
+
+// time between the host starting a race and everyone typing
+const COUNTDOWN: Duration = Duration::from_secs(3);
+// a connection that can't take a message for this long is cut off
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Player {
+    pub name: String,
+    pub progress: Progress,
+}
+
+/// Something that came in over the network, passed to the main loop as
+/// `Event::Race`.
+pub enum RaceEvent {
+    /// A player connected to the host.
+    Joined(usize, String),
+    /// A player sent their progress to the host.
+    Sent(usize, Progress),
+    /// A player disconnected from the host.
+    Left(usize),
+    /// The host sent the text for the next race.
+    Test(TestConfig, u64, String),
+    /// The host started the race, typing starts after the countdown.
+    Start(Duration),
+    /// The host passed on a player's progress.
+    Progress(String, Progress),
+    /// The host passed on that a player disconnected.
+    PlayerLeft(String),
+    /// The host gave this player a different name, another player already
+    /// had theirs.
+    Renamed(String),
+    /// The connection to the host was lost.
+    Disconnected,
+}
+
+// a host keeps a connection to every player, a player only to the host
+enum Link {
+    Host {
+        peers: Arc<Mutex<Vec<Peer>>>,
+        names: HashMap<usize, String>,
+    },
+    Client(Connection),
+}
+
+// messages go out through a writer thread, so a peer that stops reading
+// holds up its own thread rather than the main loop
+struct Connection {
+    stream: TcpStream,
+    writer: Sender<String>,
+}
+
+struct Peer {
+    id: usize,
+    connection: Connection,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        let mut out = stream.try_clone()?;
+        out.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let (writer, messages) = mpsc::channel::<String>();
+
+        // shutting the socket down on a failed write ends the reader thread
+        // too, which sees it as the other end leaving
+        thread::spawn(move || {
+            for message in messages {
+                if writeln!(out, "{}", message).is_err() {
+                    break;
+                }
+            }
+            let _ = out.shutdown(Shutdown::Both);
+        });
+
+        Ok(Connection { stream, writer })
+    }
+
+    fn send(&self, message: String) {
+        let _ = self.writer.send(message);
+    }
+}
+
+/// A race with other players over TCP.
+///
+/// The host picks the text and starts every race, players stream their
+/// progress to the host, and the host passes everyone's progress on to
+/// everyone else. Messages are lines of tab separated fields.
+pub struct Race {
+    link: Link,
+    pub name: String,
+    /// Everyone in the race, this player included.
+    pub players: Vec<Player>,
+    /// When typing starts, while the countdown is running.
+    pub starts_at: Option<Instant>,
+    pub connected: bool,
+}
+
+impl Race {
+    /// Listens for players on `port`, on every interface.
+    pub fn host<I: Send + 'static>(port: u16, name: String, tx: Sender<Event<I>>) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let peers: Arc<Mutex<Vec<Peer>>> = Arc::new(Mutex::new(vec![]));
+
+        let thread_peers = Arc::clone(&peers);
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(_) => continue
+                };
+                let reader = match stream.try_clone() {
+                    Ok(s) => s,
+                    Err(_) => continue
+                };
+                let connection = match Connection::new(stream) {
+                    Ok(c) => c,
+                    Err(_) => continue
+                };
+                thread_peers.lock().unwrap().push(Peer { id, connection });
+
+                let tx = tx.clone();
+                let peers = Arc::clone(&thread_peers);
+                thread::spawn(move || {
+                    for line in BufReader::new(reader).lines() {
+                        let line = match line {
+                            Ok(l) => l,
+                            Err(_) => break
+                        };
+                        let event = match parse_player_message(id, &line) {
+                            Some(e) => e,
+                            None => continue
+                        };
+                        if tx.send(Event::Race(event)).is_err() {
+                            return;
+                        }
+                    }
+
+                    peers.lock().unwrap().retain(|p| p.id != id);
+                    let _ = tx.send(Event::Race(RaceEvent::Left(id)));
+                });
+            }
+        });
+
+        Ok(Race::new(Link::Host { peers, names: HashMap::new() }, name))
+    }
+
+    /// Connects to a host at `addr`.
+    pub fn join<I: Send + 'static>(addr: &str, name: String, tx: Sender<Event<I>>) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let reader = stream.try_clone()?;
+        let connection = Connection::new(stream)?;
+        connection.send(format!("hello\t{}", name));
+
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let line = match line {
+                    Ok(l) => l,
+                    Err(_) => break
+                };
+                let event = match parse_host_message(&line) {
+                    Some(e) => e,
+                    None => continue
+                };
+                if tx.send(Event::Race(event)).is_err() {
+                    return;
+                }
+            }
+
+            let _ = tx.send(Event::Race(RaceEvent::Disconnected));
+        });
+
+        Ok(Race::new(Link::Client(connection), name))
+    }
+
+    fn new(link: Link, name: String) -> Self {
+        Race {
+            link,
+            players: vec![Player { name: name.clone(), progress: Progress::default() }],
+            name,
+            starts_at: None,
+            connected: true,
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.link, Link::Host { .. })
+    }
+
+    /// Sends everyone the text and starts the countdown. Only the host can
+    /// start a race.
+    pub fn start(&mut self, config: &TestConfig, seed: u64, text: &str) {
+        let test = format!(
+            "test\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            config.mode,
+            config.word_list,
+            config.game_option,
+            config.timer_option,
+            config.modifiers.join(","),
+            config.ignore.join(","),
+            seed,
+            escape(text)
+        );
+        self.broadcast(&test);
+        self.broadcast(&format!("start\t{}", COUNTDOWN.as_millis()));
+        self.on_start(COUNTDOWN);
+    }
+
+    fn on_start(&mut self, countdown: Duration) {
+        self.starts_at = Some(Instant::now() + countdown);
+        for p in self.players.iter_mut() {
+            p.progress = Progress::default();
+        }
+    }
+
+    /// Whether the countdown just ran out, true only once per race.
+    pub fn ready(&mut self) -> bool {
+        match self.starts_at {
+            Some(t) if Instant::now() >= t => {
+                self.starts_at = None;
+                true
+            },
+            _ => false
+        }
+    }
+
+    /// Shares this player's progress, if it changed.
+    pub fn update(&mut self, progress: Progress) {
+        if self.players[0].progress == progress {
+            return;
+        }
+        self.players[0].progress = progress;
+
+        match &self.link {
+            Link::Host { .. } => self.broadcast(&progress_message(&self.name, progress)),
+            Link::Client(connection) => connection.send(format!(
+                "progress\t{}\t{}\t{}",
+                progress.cursor,
+                progress.errors,
+                progress.done as u8
+            ))
+        }
+    }
+
+    /// Keeps track of players from the network. Events that change the
+    /// test itself are handled by the app.
+    pub fn on_event(&mut self, event: RaceEvent) {
+        match event {
+            RaceEvent::Joined(id, name) => {
+                // names have to be unique for progress to go to the right bar
+                let mut unique = name.clone();
+                let mut n = 1;
+                while self.players.iter().any(|p| p.name == unique) {
+                    n += 1;
+                    unique = format!("{} ({})", name, n);
+                }
+                self.players.push(Player { name: unique.clone(), progress: Progress::default() });
+                if unique != name {
+                    self.send_to(id, &format!("name\t{}", unique));
+                }
+                if let Link::Host { names, .. } = &mut self.link {
+                    names.insert(id, unique);
+                }
+
+                // everyone gets the full list, the new player included
+                let messages: Vec<String> = self.players
+                    .iter()
+                    .map(|p| progress_message(&p.name, p.progress))
+                    .collect();
+                for m in messages {
+                    self.broadcast(&m);
+                }
+            },
+            RaceEvent::Sent(id, progress) => {
+                let name = match &self.link {
+                    Link::Host { names, .. } => names.get(&id).cloned(),
+                    Link::Client(_) => None
+                };
+                if let Some(name) = name {
+                    self.set_progress(&name, progress);
+                    self.broadcast(&progress_message(&name, progress));
+                }
+            },
+            RaceEvent::Left(id) => {
+                let name = match &mut self.link {
+                    Link::Host { names, .. } => names.remove(&id),
+                    Link::Client(_) => None
+                };
+                if let Some(name) = name {
+                    self.players.retain(|p| p.name != name);
+                    self.broadcast(&format!("left\t{}", name));
+                }
+            },
+            RaceEvent::Start(countdown) => self.on_start(countdown),
+            RaceEvent::Progress(name, progress) => self.set_progress(&name, progress),
+            RaceEvent::PlayerLeft(name) => self.players.retain(|p| p.name != name),
+            RaceEvent::Renamed(name) => {
+                self.players.retain(|p| p.name != name);
+                self.players[0].name = name.clone();
+                self.name = name;
+            },
+            RaceEvent::Disconnected => self.connected = false,
+            RaceEvent::Test(..) => {}
+        }
+    }
+
+    fn set_progress(&mut self, name: &str, progress: Progress) {
+        // this player's own progress is already known, and the host echoes
+        // it back to everyone
+        if name == self.name {
+            return;
+        }
+
+        match self.players.iter_mut().find(|p| p.name == name) {
+            Some(p) => p.progress = progress,
+            None => self.players.push(Player { name: name.to_string(), progress })
+        }
+    }
+
+    fn send_to(&self, id: usize, message: &str) {
+        if let Link::Host { peers, .. } = &self.link {
+            if let Some(p) = peers.lock().unwrap().iter().find(|p| p.id == id) {
+                p.connection.send(message.to_string());
+            }
+        }
+    }
+
+    // players that can't be written to are dropped by their reader thread
+    fn broadcast(&self, message: &str) {
+        if let Link::Host { peers, .. } = &self.link {
+            for p in peers.lock().unwrap().iter() {
+                p.connection.send(message.to_string());
+            }
+        }
+    }
+}
+
+// reader threads hold their own handles to the sockets, so they have to be
+// shut down for the other end to see the connection close
+impl Drop for Race {
+    fn drop(&mut self) {
+        match &self.link {
+            Link::Host { peers, .. } => {
+                for p in peers.lock().unwrap().iter() {
+                    let _ = p.connection.stream.shutdown(Shutdown::Both);
+                }
+            },
+            Link::Client(connection) => {
+                let _ = connection.stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+fn progress_message(name: &str, progress: Progress) -> String {
+    format!("progress\t{}\t{}\t{}\t{}", name, progress.cursor, progress.errors, progress.done as u8)
+}
+
+fn parse_progress<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<Progress> {
+    Some(Progress {
+        cursor: fields.next()?.parse().ok()?,
+        errors: fields.next()?.parse().ok()?,
+        done: fields.next()? == "1",
+    })
+}
+
+// messages a player sends the host
+fn parse_player_message(id: usize, line: &str) -> Option<RaceEvent> {
+    let mut fields = line.split('\t');
+    match fields.next()? {
+        "hello" => Some(RaceEvent::Joined(id, fields.next()?.to_string())),
+        "progress" => Some(RaceEvent::Sent(id, parse_progress(fields)?)),
+        _ => None
+    }
+}
+
+// messages the host sends a player
+fn parse_host_message(line: &str) -> Option<RaceEvent> {
+    let mut fields = line.split('\t');
+    match fields.next()? {
+        "test" => {
+            let config = TestConfig {
+                mode: fields.next()?.to_string(),
+                word_list: fields.next()?.to_string(),
+                game_option: fields.next()?.to_string(),
+                timer_option: fields.next()?.to_string(),
+                modifiers: split_list(fields.next()?),
+                ignore: split_list(fields.next()?),
+            };
+            let seed = fields.next()?.parse().ok()?;
+            let text = unescape(fields.next()?);
+            Some(RaceEvent::Test(config, seed, text))
+        },
+        "start" => Some(RaceEvent::Start(Duration::from_millis(fields.next()?.parse().ok()?))),
+        "progress" => {
+            let name = fields.next()?.to_string();
+            Some(RaceEvent::Progress(name, parse_progress(fields)?))
+        },
+        "left" => Some(RaceEvent::PlayerLeft(fields.next()?.to_string())),
+        "name" => Some(RaceEvent::Renamed(fields.next()?.to_string())),
+        _ => None
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use rype_core::{Difficulty, StopOn};
+use rype_core::stats::Keystroke;
+
+use super::history::{split_list, TestConfig};
+use super::paths::{data_dir, write_data_file};
+
+const HEADER: &str = "rype replay 1";
+
+/// One key press in a replay.
+pub struct ReplayKey {
+    /// Time since the first key.
+    pub at: Duration,
+    pub key: char,
+    /// Position of the caret when the key was pressed.
+    pub index: usize,
+}
+
+/// Everything needed to play a test back: its settings, the text, and every
+/// key pressed.
+pub struct Replay {
+    pub config: TestConfig,
+    pub seed: u64,
+    pub text: String,
+    pub keys: Vec<ReplayKey>,
+    /// When the test ended, which can be after the last key.
+    pub end: Duration,
+    pub stop_on: Option<StopOn>,
+    pub difficulty: Difficulty,
+    pub lock_correct: bool,
+}
+
+impl Replay {
+    pub fn new(config: TestConfig, seed: u64, text: &str, keystrokes: &[Keystroke], end: Duration) -> Self {
+        let mut at = Duration::from_secs(0);
+        let keys = keystrokes
+            .iter()
+            .map(|k| {
+                at += k.latency.unwrap_or_default();
+                ReplayKey {
+                    at,
+                    key: k.actual,
+                    index: k.index,
+                }
+            })
+            .collect();
+
+        Replay {
+            config,
+            seed,
+            text: text.to_string(),
+            keys,
+            end,
+            stop_on: None,
+            difficulty: Difficulty::Normal,
+            lock_correct: false,
+        }
+    }
+
+    /// Sets the strict mode settings the test was typed with, so mistakes and
+    /// backspaces are played back the way they were handled.
+    pub fn rules(mut self, stop_on: Option<StopOn>, difficulty: Difficulty, lock_correct: bool) -> Self {
+        self.stop_on = stop_on;
+        self.difficulty = difficulty;
+        self.lock_correct = lock_correct;
+        self
+    }
+
+    /// Writes the replay to `$XDG_DATA_HOME/rype/replays/<timestamp>.replay`
+    /// and returns its file name.
+    pub fn save(&self) -> io::Result<String> {
+        let dir = replays_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let name = format!("{}.replay", timestamp);
+        write_data_file(Some(&dir.join(&name)), &self.contents())?;
+        Ok(name)
+    }
+
+    /// Loads a replay saved by `save`.
+    pub fn load_saved(name: &str) -> io::Result<Self> {
+        let dir = replays_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+        Replay::load(&dir.join(name))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Replay::parse(&contents)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a replay file"))
+    }
+
+    // a few header lines of settings, up to `end`, then one line per key with
+    // the milliseconds since the first key, the key, and the caret position,
+    // fields separated by tabs
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+
+        // settings added later are missing from older replays
+        let mut fields = HashMap::new();
+        for line in lines.by_ref() {
+            let (name, value) = line.split_once('\t')?;
+            fields.insert(name, value);
+            if name == "end" {
+                break;
+            }
+        }
+
+        let config = TestConfig {
+            mode: fields.get("mode")?.to_string(),
+            word_list: fields.get("word_list")?.to_string(),
+            game_option: fields.get("game")?.to_string(),
+            timer_option: fields.get("timer")?.to_string(),
+            modifiers: split_list(fields.get("modifiers")?),
+            ignore: split_list(fields.get("ignore").unwrap_or(&"")),
+        };
+        let seed = fields.get("seed")?.parse().ok()?;
+        let text = unescape(fields.get("text")?);
+        let end = Duration::from_millis(fields.get("end")?.parse().ok()?);
+        let stop_on = fields.get("stop_on").and_then(|s| StopOn::from_name(s));
+        let difficulty = match fields.get("difficulty") {
+            Some(d) => Difficulty::from_name(d)?,
+            None => Difficulty::Normal
+        };
+        let lock_correct = fields.get("lock_correct") == Some(&"1");
+
+        let mut keys = vec![];
+        for line in lines {
+            let mut fields = line.split('\t');
+            keys.push(ReplayKey {
+                at: Duration::from_millis(fields.next()?.parse().ok()?),
+                key: unescape(fields.next()?).chars().next()?,
+                index: fields.next()?.parse().ok()?,
+            });
+        }
+
+        Some(Replay {
+            config,
+            seed,
+            text,
+            keys,
+            end,
+            stop_on,
+            difficulty,
+            lock_correct,
+        })
+    }
+
+    fn contents(&self) -> String {
+        let mut out = format!(
+            "{}\nmode\t{}\nword_list\t{}\ngame\t{}\ntimer\t{}\nmodifiers\t{}\nignore\t{}\nstop_on\t{}\ndifficulty\t{}\nlock_correct\t{}\nseed\t{}\ntext\t{}\nend\t{}\n",
+            HEADER,
+            self.config.mode,
+            self.config.word_list,
+            self.config.game_option,
+            self.config.timer_option,
+            self.config.modifiers.join(","),
+            self.config.ignore.join(","),
+            self.stop_on.map(StopOn::name).unwrap_or("-"),
+            self.difficulty.name(),
+            self.lock_correct as u8,
+            self.seed,
+            escape(&self.text),
+            self.end.as_millis()
+        );
+
+        for k in &self.keys {
+            out.push_str(&format!("{}\t{}\t{}\n", k.at.as_millis(), escape(&k.key.to_string()), k.index));
+        }
+
+        out
+    }
+}
+
+fn replays_dir() -> Option<PathBuf> {
+    data_dir().map(|d| d.join("replays"))
+}
+
+// keeps everything on one line: newlines, tabs, backspaces and the skip fill
+// get backslash escapes
+pub fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\x08' => out.push_str("\\b"),
+            '\0' => out.push_str("\\0"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('b') => out.push('\x08'),
+            Some('0') => out.push('\0'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_round_trips_through_its_file() {
+        let config = TestConfig {
+            mode: "code".to_string(),
+            word_list: "rust".to_string(),
+            game_option: "50".to_string(),
+            timer_option: "30".to_string(),
+            modifiers: vec!["punctuation".to_string(), "numbers".to_string()],
+            ignore: vec!["case".to_string()],
+        };
+        let keys = ['a', '\n', '\t', '\\', '\0', '\x08']
+            .iter()
+            .enumerate()
+            .map(|(i, &key)| ReplayKey { at: Duration::from_millis(120 * i as u64), key, index: i })
+            .collect();
+        let replay = Replay {
+            config,
+            seed: 42,
+            text: "fn main() {\n\tlet s = \"a\\\\b\";\n}".to_string(),
+            keys,
+            end: Duration::from_millis(900),
+            stop_on: Some(StopOn::Word),
+            difficulty: Difficulty::Expert,
+            lock_correct: true,
+        };
+
+        let parsed = Replay::parse(&replay.contents()).unwrap();
+        assert!(parsed.config == replay.config);
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.text, replay.text);
+        assert_eq!(parsed.end, replay.end);
+        assert_eq!(parsed.stop_on.map(StopOn::name), Some("word"));
+        assert_eq!(parsed.difficulty.name(), "expert");
+        assert!(parsed.lock_correct);
+
+        assert_eq!(parsed.keys.len(), replay.keys.len());
+        for (a, b) in parsed.keys.iter().zip(&replay.keys) {
+            assert_eq!((a.at, a.key, a.index), (b.at, b.key, b.index));
+        }
+    }
+
+    #[test]
+    fn escaped_text_stays_on_one_line() {
+        let text = "a\nb\tc\\d\0e\x08";
+        let escaped = escape(text);
+        assert!(!escaped.contains('\n') && !escaped.contains('\t'));
+        assert_eq!(unescape(&escaped), text);
+    }
+}
@@ -0,0 +1,48 @@
+use std::{io, panic, process};
+use crossterm::{
+    cursor,
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}
+};
+
+/// Keeps the terminal in raw mode on the alternate screen for as long as it
+/// lives, and puts it back the way it was when dropped, whichever way `main`
+/// returns.
+pub struct ScreenGuard;
+
+impl ScreenGuard {
+    pub fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        // dropping the guard undoes raw mode if the screen can't be switched
+        let guard = ScreenGuard;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for ScreenGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Restores the terminal before the panic message is printed, so it can be
+/// read and the shell is usable afterwards.
+///
+/// The process exits after any panic, since a panic on the race or timer
+/// thread would otherwise leave the main loop drawing into the restored
+/// terminal.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+        process::exit(101);
+    }));
+}
+
+// errors are ignored, there is nothing left to do about a terminal that's gone
+fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    io
+};
+
+use super::paths::data_dir;
+
+const DEFAULT_SNIPPETS: [(&str, &str); 3] = [
+    ("rust", include_str!("snippets/rust.txt")),
+    ("python", include_str!("snippets/python.txt")),
+    ("js", include_str!("snippets/js.txt")),
+];
+
+/// A piece of source code to type.
+pub struct Snippet {
+    pub language: String,
+    pub text: String,
+}
+
+impl Snippet {
+    fn new(language: &str, text: &str) -> Option<Self> {
+        // tabs become four spaces and trailing whitespace can't be seen, so
+        // neither should have to be typed
+        let lines: Vec<String> = text
+            .lines()
+            .map(|l| l.replace('\t', "    ").trim_end().to_string())
+            .collect();
+        let text = lines.join("\n").trim_matches('\n').to_string();
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        Some(Snippet {
+            language: language.to_string(),
+            text,
+        })
+    }
+}
+
+/// The bundled snippets followed by every file in `<data dir>/snippets/<language>/`.
+pub fn load_snippets() -> io::Result<Vec<Snippet>> {
+    let mut snippets = vec![];
+    for (language, contents) in DEFAULT_SNIPPETS.iter() {
+        // bundled snippets are kept in one file per language, split by `%%` lines
+        for text in contents.split("\n%%\n") {
+            snippets.extend(Snippet::new(language, text));
+        }
+    }
+
+    let dir = match data_dir() {
+        Some(d) => d.join("snippets"),
+        None => return Ok(snippets)
+    };
+
+    for (language, _) in DEFAULT_SNIPPETS.iter() {
+        let entries = match fs::read_dir(dir.join(language)) {
+            Ok(e) => e,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        for path in paths {
+            // a bad file shouldn't keep the game from starting
+            if let Ok(text) = fs::read_to_string(&path) {
+                snippets.extend(Snippet::new(language, &text));
+            }
+        }
+    }
+
+    Ok(snippets)
+}
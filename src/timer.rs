@@ -0,0 +1,82 @@
+use std::{
+    thread,
+    sync::{
+        mpsc::Sender,
+        Arc,
+        atomic::{AtomicBool, Ordering}
+    },
+    time::{Duration, Instant}
+};
+
+use super::Event;
+
+/// Clock for a single test.
+///
+/// Runs on its own thread and sends `Event::TimerUpdate` every time a whole
+/// second passes, with the seconds left for a countdown or the seconds elapsed
+/// otherwise. Everything is measured from the `Instant` the timer was started
+/// at, so it does not drift with the tick rate. `speed` scales how fast test
+/// time passes, for replays played back faster than they were typed.
+pub struct Timer {
+    start: Instant,
+    speed: f64,
+    duration: Option<Duration>,
+    running: Arc<AtomicBool>,
+}
+
+impl Timer {
+    pub fn start<I: Send + 'static>(tx: Sender<Event<I>>, countdown: Option<u16>, speed: f64) -> Self {
+        let start = Instant::now();
+        let duration = countdown.map(|s| Duration::from_secs(s.into()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread_running = Arc::clone(&running);
+        thread::spawn(move || {
+            loop {
+                // sleep until the next whole second is crossed
+                let elapsed = start.elapsed().mul_f64(speed);
+                thread::sleep((Duration::from_secs(elapsed.as_secs() + 1) - elapsed).div_f64(speed));
+
+                if !thread_running.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let elapsed = start.elapsed().mul_f64(speed);
+                let clock = match duration {
+                    Some(d) => d.saturating_sub(elapsed).as_secs_f64().ceil() as u64,
+                    None => elapsed.as_secs()
+                };
+
+                let done = duration.is_some() && clock == 0;
+                if tx.send(Event::TimerUpdate(clock as u16)).is_err() || done {
+                    break;
+                }
+            }
+        });
+
+        Timer {
+            start,
+            speed,
+            duration,
+            running,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        let elapsed = self.start.elapsed().mul_f64(self.speed);
+        match self.duration {
+            Some(d) => elapsed.min(d),
+            None => elapsed
+        }
+    }
+
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
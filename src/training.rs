@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    time::Duration
+};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand_chacha::ChaCha8Rng;
+use rype_core::stats::{bigram_stats, key_stats, KeyStats, Keystroke};
+
+use super::paths::{data_dir, read_data_file, write_data_file};
+
+// letters are unlocked in order of how common they are in english
+const LETTERS: &str = "etaoinshrdlcumwfgypbvkjxqz";
+const START_LETTERS: usize = 6;
+// a letter needs this many presses before its stats are trusted
+const MIN_PRESSES: usize = 20;
+const MAX_ERROR_RATE: f64 = 0.05;
+// about 35 wpm
+const TARGET_LATENCY: Duration = Duration::from_millis(340);
+const TARGETS: usize = 3;
+const WEAK_BIGRAMS: usize = 3;
+// with fewer words than this to pick from, locked letters are let back in
+const MIN_POOL: usize = 20;
+
+/// Per key and per bigram stats summed over every test, backed by
+/// `$XDG_DATA_HOME/rype/keys`.
+pub struct KeyHistory {
+    path: Option<PathBuf>,
+    pub keys: HashMap<char, KeyStats>,
+    pub bigrams: HashMap<(char, char), KeyStats>,
+}
+
+impl KeyHistory {
+    /// Reads the per-key and per-bigram stats kept so far.
+    pub fn load() -> io::Result<Self> {
+        let path = data_dir().map(|d| d.join("keys"));
+        let mut keys = HashMap::new();
+        let mut bigrams = HashMap::new();
+
+        for (k, stats) in read_data_file(path.as_deref())?.lines().filter_map(from_line) {
+            match k[..] {
+                [c] => { keys.insert(c, stats); },
+                [a, b] => { bigrams.insert((a, b), stats); },
+                _ => {}
+            }
+        }
+
+        Ok(KeyHistory { path, keys, bigrams })
+    }
+
+    /// Adds the keystrokes of a finished test and rewrites the stats file.
+    pub fn add(&mut self, keystrokes: &[Keystroke]) -> io::Result<()> {
+        // whitespace would break up the file format and says nothing about
+        // which letters need work
+        for (c, stats) in key_stats(keystrokes) {
+            if !c.is_whitespace() {
+                self.keys.entry(c).or_default().merge(&stats);
+            }
+        }
+        for ((a, b), stats) in bigram_stats(keystrokes) {
+            if !a.is_whitespace() && !b.is_whitespace() {
+                self.bigrams.entry((a, b)).or_default().merge(&stats);
+            }
+        }
+
+        let mut contents = String::new();
+        for (c, stats) in &self.keys {
+            contents.push_str(&to_line(&c.to_string(), stats));
+        }
+        for ((a, b), stats) in &self.bigrams {
+            contents.push_str(&to_line(&format!("{}{}", a, b), stats));
+        }
+        write_data_file(self.path.as_deref(), &contents)
+    }
+
+    /// Works out which letters are unlocked and which of them need the most
+    /// practice.
+    ///
+    /// Letters unlock one at a time once every letter before them is typed
+    /// accurately and quickly enough, starting from the most common few.
+    pub fn lesson(&self) -> Lesson {
+        let letters: Vec<char> = LETTERS.chars().collect();
+        let mut unlocked = START_LETTERS;
+        while unlocked < letters.len() && letters[..unlocked].iter().all(|c| confident(self.keys.get(c))) {
+            unlocked += 1;
+        }
+        let letters = letters[..unlocked].to_vec();
+
+        let mut targets = letters.clone();
+        targets.sort_by(|a, b| weakness(self.keys.get(b)).total_cmp(&weakness(self.keys.get(a))));
+        targets.truncate(TARGETS);
+
+        // only bigrams with enough presses to go on, and only ones that can
+        // actually show up in the words picked
+        let mut bigrams: Vec<(char, char)> = self.bigrams
+            .iter()
+            .filter(|((a, b), stats)| {
+                stats.presses >= MIN_PRESSES
+                    && letters.contains(a)
+                    && letters.contains(b)
+                    && !confident(Some(stats))
+            })
+            .map(|(k, _)| *k)
+            .collect();
+        bigrams.sort_by(|a, b| {
+            weakness(self.bigrams.get(b))
+                .total_cmp(&weakness(self.bigrams.get(a)))
+                .then(a.cmp(b))
+        });
+        bigrams.truncate(WEAK_BIGRAMS);
+
+        Lesson { letters, targets, bigrams }
+    }
+}
+
+/// What a training test is built around.
+pub struct Lesson {
+    /// Letters unlocked so far, words are made up of only these.
+    pub letters: Vec<char>,
+    /// The weakest unlocked letters.
+    pub targets: Vec<char>,
+    /// Slow or error prone letter pairs.
+    pub bigrams: Vec<(char, char)>,
+}
+
+impl Lesson {
+    /// Picks `count` words out of the first `r`, favouring ones with target
+    /// letters and weak bigrams in them.
+    ///
+    /// Few common words are made of only the first letters unlocked, so the
+    /// rest of the list is searched before locked letters are let in.
+    pub fn pick_words(&self, words: &[String], r: usize, count: usize, rng: &mut ChaCha8Rng) -> Vec<String> {
+        let mut pool = self.unlocked_words(&words[..r]);
+        if pool.len() < MIN_POOL {
+            pool = self.unlocked_words(words);
+        }
+        if pool.len() < MIN_POOL {
+            pool = words[..r].iter().collect();
+        }
+
+        let weights: Vec<usize> = pool.iter().map(|w| self.weight(w)).collect();
+        let dist = WeightedIndex::new(&weights).unwrap();
+
+        let mut out: Vec<String> = vec![];
+        let mut last_word = "";
+        while out.len() < count {
+            let word = pool[dist.sample(rng)];
+
+            // a pool of one word can't avoid repeats
+            if word == last_word && pool.len() > 1 {
+                continue;
+            }
+
+            out.push(word.clone());
+            last_word = word;
+        }
+
+        out
+    }
+
+    fn unlocked_words<'a>(&self, words: &'a [String]) -> Vec<&'a String> {
+        words
+            .iter()
+            .filter(|w| w.chars().all(|c| self.letters.contains(&c.to_ascii_lowercase())))
+            .collect()
+    }
+
+    fn weight(&self, word: &str) -> usize {
+        let chars: Vec<char> = word.chars().map(|c| c.to_ascii_lowercase()).collect();
+        let targets = self.targets.iter().filter(|t| chars.contains(t)).count();
+        let bigrams = self.bigrams
+            .iter()
+            .filter(|(a, b)| chars.windows(2).any(|w| w[0] == *a && w[1] == *b))
+            .count();
+
+        1 + 4 * targets + 2 * bigrams
+    }
+}
+
+fn confident(stats: Option<&KeyStats>) -> bool {
+    match stats {
+        Some(s) => {
+            s.presses >= MIN_PRESSES
+                && s.error_rate() <= MAX_ERROR_RATE
+                && s.average_latency().is_some_and(|l| l <= TARGET_LATENCY)
+        },
+        None => false
+    }
+}
+
+// 1.0 is right on the limit for both error rate and speed, keys without
+// enough presses yet come before everything else
+fn weakness(stats: Option<&KeyStats>) -> f64 {
+    match stats {
+        Some(s) if s.presses >= MIN_PRESSES => {
+            let latency = s.average_latency().unwrap_or(TARGET_LATENCY);
+            s.error_rate() / MAX_ERROR_RATE + latency.as_secs_f64() / TARGET_LATENCY.as_secs_f64()
+        },
+        _ => f64::INFINITY
+    }
+}
+
+// stats are stored one key per line, fields separated by tabs
+fn to_line(key: &str, stats: &KeyStats) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}\n",
+        key,
+        stats.presses,
+        stats.errors,
+        stats.total_latency.as_micros(),
+        stats.timed_presses
+    )
+}
+
+fn from_line(line: &str) -> Option<(Vec<char>, KeyStats)> {
+    let mut fields = line.split('\t');
+    let key = fields.next()?.chars().collect();
+    let stats = KeyStats {
+        presses: fields.next()?.parse().ok()?,
+        errors: fields.next()?.parse().ok()?,
+        total_latency: Duration::from_micros(fields.next()?.parse().ok()?),
+        timed_presses: fields.next()?.parse().ok()?,
+    };
+
+    Some((key, stats))
+}
@@ -1,16 +1,27 @@
 use tui::{
     Frame,
     backend::Backend,
-    widgets::{Block, BorderType, Borders, Paragraph, Tabs, Wrap},
+    widgets::{Block, BorderType, Borders, Paragraph, Tabs},
     layout::{Alignment, Rect, Layout, Direction, Constraint},
-    style::{Style, Color},
+    style::{Style, Color, Modifier},
     text::{Spans, Span}
 };
 
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Instant;
+
+use rype_core::stats::KeyStats;
+use rype_core::text::{self, graphemes};
+
 use super::App;
 use super::app::FocusedWindow;
 use super::app::GameState;
+use super::app::Mode;
+use super::app::Heatmap;
+use super::training::Lesson;
 use super::widgets::alignedtabs::AlignedTabs;
+use super::widgets::keyboard::{base_key, Keyboard};
 
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let title_chunk = Block::default()
@@ -31,76 +42,246 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         )
         .split(f.size());
 
-    let game_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .horizontal_margin(chunks[1].width / 5)
-        .vertical_margin(chunks[1].height / 5)
-        .constraints(
-            [
-            Constraint::Ratio(1, 3), // timer chunk
-            Constraint::Ratio(1, 3), // typing chunk
-            Constraint::Ratio(1, 3)  // padding chunk, not visible
-            ].as_ref()
-        )
-        .split(chunks[1]);
-
-    let timer_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-            Constraint::Min(0),   // padding chunk, not visible
-            Constraint::Length(1) // timer chunk
-            ].as_ref()
-        )
-        .split(game_chunks[0]);
+    let (timer_chunk, typing_chunk) = game_chunks(chunks[1], app.typing_lines);
 
+    // tab groups get the width they need and the word lists take what is left
     let header_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints(
             [
-            Constraint::Percentage(50),
-            Constraint::Percentage(50)
+            Constraint::Length(tabs_width(&app.mode_options) + 2),     // mode
+            Constraint::Length(tabs_width(&app.game_options) + 1),     // game options
+            Constraint::Min(0),                                        // word list options
+            Constraint::Length(tabs_width(&app.modifier_options) + 2), // modifiers
+            Constraint::Length(13),                                    // personal best
+            Constraint::Length(tabs_width(&app.timer_options) + 2)     // timer options
             ].as_ref()
         )
         .split(chunks[0]);
 
     match app.state {
         GameState::Pre => {
-            render_header_widgets(f, header_chunks[0], header_chunks[1], app);
-            // render_timer(f, timer_chunks[1], app.current_timer);
-            let timer = Paragraph::new("timer here")
-                .style(Style::default().fg(Color::Blue))
-                .alignment(Alignment::Left);
-            f.render_widget(timer, timer_chunks[1]);
+            render_header_widgets(f, &header_chunks, app);
+            render_timer(f, timer_chunk, app);
+            render_race(f, chunks[1], timer_chunk, app);
 
             // let typing_section = Paragraph::new("type here")
             //     // .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded))
             //     .alignment(Alignment::Left);
             // f.render_widget(typing_section, game_chunks[1]);
-            render_typing_section(f, game_chunks[1], app);
+            render_typing_section(f, typing_chunk, app);
 
-            let footer = Paragraph::new("Footer")
+            let footer_text = match &app.race {
+                Some(race) if !race.connected => "lost the connection to the host",
+                Some(race) if race.starts_at.is_some() => "get ready",
+                Some(race) if race.is_host() => "enter: start the race",
+                Some(_) => "waiting for the host to start",
+                None => "Footer"
+            };
+            let footer = Paragraph::new(footer_text)
                 .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded))
                 .alignment(Alignment::Center);
             f.render_widget(footer, chunks[2]);
         },
         GameState::During => {
-            // render_timer(f, timer_chunks[1], app);
-            let timer = Paragraph::new(format!("timer started {}", app.cursor_index))
-                .style(Style::default().fg(Color::Blue))
-                .alignment(Alignment::Left);
-            f.render_widget(timer, timer_chunks[1]);
+            render_timer(f, timer_chunk, app);
+            render_race(f, chunks[1], timer_chunk, app);
 
             // let typing_section = Paragraph::new("type here")
             //     // .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded))
             //     .alignment(Alignment::Left);
             // f.render_widget(typing_section, game_chunks[1]);
-            render_typing_section(f, game_chunks[1], app);
+            render_typing_section(f, typing_chunk, app);
         },
-        GameState::Post => {}
+        GameState::Post => {
+            render_results(f, chunks[1], app);
+
+            let footer = Paragraph::new("enter: restart  tab: errors/latency  q: quit")
+                .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded))
+                .alignment(Alignment::Center);
+            f.render_widget(footer, chunks[2]);
+        }
     }
 }
 
+fn render_results<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let result = match &app.result {
+        Some(r) => r,
+        None => return
+    };
+
+    let label = Style::default().fg(Color::Blue);
+    let mut lines = vec![
+        Spans::from(vec![
+            Span::styled("wpm ", label),
+            Span::raw(format!("{:.0}", result.wpm)),
+        ]),
+        Spans::from(vec![
+            Span::styled("raw ", label),
+            Span::raw(format!("{:.0}", result.raw_wpm)),
+        ]),
+        Spans::from(vec![
+            Span::styled("acc ", label),
+            Span::raw(format!("{:.1}%", result.accuracy)),
+        ]),
+        Spans::from(vec![
+            Span::styled("chars ", label),
+            Span::styled(result.correct.to_string(), Style::default().fg(Color::Green)),
+            Span::raw("/"),
+            Span::styled(result.incorrect.to_string(), Style::default().fg(Color::Red)),
+            Span::raw("/"),
+            Span::styled(result.extra.to_string(), Style::default().fg(Color::Magenta)),
+            Span::raw("/"),
+            Span::styled(result.skipped.to_string(), Style::default().fg(Color::Yellow)),
+        ]),
+        Spans::from(vec![
+            Span::styled("time ", label),
+            Span::raw(format!("{:.1}s", result.elapsed.as_secs_f64())),
+        ]),
+        Spans::from(vec![
+            Span::styled("seed ", label),
+            Span::raw(app.seed.to_string()),
+        ]),
+    ];
+
+    let ignored = app.session.rules().comparison.names();
+    if !ignored.is_empty() {
+        lines.push(Spans::from(vec![
+            Span::styled("ignoring ", label),
+            Span::raw(ignored.join(", ")),
+        ]));
+    }
+
+    if app.session.failed() {
+        lines.insert(0, Spans::from(Span::styled(
+            format!("failed ({})", app.session.rules().difficulty.name()),
+            Style::default().fg(Color::Red)
+        )));
+    }
+
+    if let Some(quote) = app.quote() {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(vec![
+            Span::raw(quote.author.as_str()),
+            Span::styled(format!(", {}", quote.source), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+
+    // the keyboard goes under the results, a blank line between them, and
+    // both are centered together
+    let (keyboard_width, keyboard_height) = Keyboard::size();
+    let keyboard_width = keyboard_width + 2;
+    let keyboard_height = keyboard_height + 2;
+    let show_keyboard = area.height >= lines.len() as u16 + 1 + keyboard_height
+        && area.width >= keyboard_width;
+
+    let height = if show_keyboard {
+        lines.len() as u16 + 1 + keyboard_height
+    } else {
+        (lines.len() as u16).min(area.height)
+    };
+    let results_area = Rect {
+        y: area.y + (area.height - height) / 2,
+        height: (lines.len() as u16).min(height),
+        ..area
+    };
+
+    let results = Paragraph::new(lines)
+        .alignment(Alignment::Center);
+    f.render_widget(results, results_area);
+
+    if show_keyboard {
+        let keyboard_area = Rect {
+            x: area.x + (area.width - keyboard_width) / 2,
+            y: results_area.bottom() + 1,
+            width: keyboard_width,
+            height: keyboard_height,
+        };
+        let title = match app.heatmap {
+            Heatmap::Errors => "errors",
+            Heatmap::Latency => "latency",
+        };
+        let keyboard = Keyboard::new(heatmap_values(&result.keys, app.heatmap))
+            .block(Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Rounded));
+        f.render_widget(keyboard, keyboard_area);
+    }
+}
+
+// Error rates are scaled so a key missed one time in five is already red.
+// Latencies are spread between the fastest and slowest key of the test.
+fn heatmap_values(keys: &HashMap<char, KeyStats>, heatmap: Heatmap) -> HashMap<char, f64> {
+    let mut merged: HashMap<char, KeyStats> = HashMap::new();
+    for (c, stats) in keys {
+        merged.entry(base_key(*c)).or_default().merge(stats);
+    }
+
+    match heatmap {
+        Heatmap::Errors => merged
+            .iter()
+            .map(|(c, s)| (*c, (s.error_rate() * 5.0).min(1.0)))
+            .collect(),
+        Heatmap::Latency => {
+            let latencies: Vec<(char, f64)> = merged
+                .iter()
+                .filter_map(|(c, s)| s.average_latency().map(|l| (*c, l.as_secs_f64())))
+                .collect();
+            let min = latencies.iter().map(|(_, l)| *l).fold(f64::INFINITY, f64::min);
+            let max = latencies.iter().map(|(_, l)| *l).fold(0.0, f64::max);
+            let range = max - min;
+
+            latencies
+                .into_iter()
+                .map(|(c, l)| (c, if range > 0.0 { (l - min) / range } else { 0.0 }))
+                .collect()
+        }
+    }
+}
+
+// The typing chunk is exactly as tall as the viewport and centered in the game
+// area, with the timer on the line above it. Layout can't center a fixed height
+// chunk reliably, so it is done by hand.
+fn game_chunks(area: Rect, typing_lines: usize) -> (Rect, Rect) {
+    let margin = area.width / 5;
+    let height = (typing_lines as u16).min(area.height.saturating_sub(1));
+    let typing_chunk = Rect {
+        x: area.x + margin,
+        y: area.y + 1 + (area.height.saturating_sub(1) - height) / 2,
+        width: area.width - 2 * margin,
+        height,
+    };
+    let timer_chunk = Rect {
+        y: typing_chunk.y - 1,
+        height: 1,
+        ..typing_chunk
+    };
+
+    (timer_chunk, typing_chunk)
+}
+
+// width of a row of tabs with their padding and dividers
+fn tabs_width(titles: &[&str]) -> u16 {
+    let text: usize = titles.iter().map(|t| t.chars().count()).sum();
+    (text + 3 * titles.len()).saturating_sub(1) as u16
+}
+
+fn render_mode_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool, app: &App) {
+    let options = app.mode_options.iter().cloned().map(Spans::from).collect();
+    let mut mode_options_tabs = Tabs::new(options)
+        .select(app.selected_mode_tab)
+        .highlight_style(Style::default().fg(Color::Green));
+
+    let mut b = Block::default()
+        .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
+        .border_type(BorderType::Rounded);
+
+    if focused {
+        b = b.border_style(Style::default().fg(Color::Red));
+    }
+
+    mode_options_tabs = mode_options_tabs.block(b);
+    f.render_widget(mode_options_tabs, area);
+}
+
 fn render_game_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool, app: &App) {
     let options = app.game_options.iter().cloned().map(Spans::from).collect();
     let mut game_options_tabs = Tabs::new(options)
@@ -108,7 +289,7 @@ fn render_game_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool,
         .highlight_style(Style::default().fg(Color::Green));
 
     let mut b = Block::default()
-        .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
+        .borders(Borders::TOP | Borders::BOTTOM)
         .border_type(BorderType::Rounded);
 
     if focused {
@@ -119,12 +300,45 @@ fn render_game_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool,
     f.render_widget(game_options_tabs, area);
 }
 
+fn render_word_list_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool, app: &App) {
+    // the daily test always uses the built in list
+    let (style, highlight_style) = match app.mode() {
+        Mode::Daily => (Style::default().fg(Color::DarkGray), Style::default().fg(Color::DarkGray)),
+        _ => (Style::default(), Style::default().fg(Color::Green))
+    };
+
+    let options = app.word_lists.iter().map(|l| Spans::from(Span::styled(l.name.as_str(), style))).collect();
+    let mut word_list_tabs = AlignedTabs::new(options)
+        .select(app.selected_word_list)
+        .alignment(Alignment::Center)
+        .highlight_style(highlight_style);
+
+    let mut b = Block::default()
+        .borders(Borders::TOP | Borders::BOTTOM)
+        .border_type(BorderType::Rounded);
+
+    if focused {
+        b = b.border_style(Style::default().fg(Color::Red));
+    }
+
+    word_list_tabs = word_list_tabs.block(b);
+    f.render_widget(word_list_tabs, area);
+}
+
 fn render_timer_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool, app: &App) {
-    let options = app.timer_options.iter().cloned().map(Spans::from).collect();
+    // quotes, code and custom text always end with the text, so there is no
+    // length to pick
+    let (style, highlight_style) = match app.mode() {
+        Mode::Time | Mode::Words | Mode::Train => (Style::default(), Style::default().fg(Color::Green)),
+        Mode::Quote | Mode::Code | Mode::Custom | Mode::Drill => (Style::default().fg(Color::DarkGray), Style::default().fg(Color::DarkGray)),
+        Mode::Daily => (Style::default(), Style::default().fg(Color::Green))
+    };
+
+    let options = app.timer_options.iter().map(|o| Spans::from(Span::styled(*o, style))).collect();
     let mut timer_options_tabs = AlignedTabs::new(options)
         .select(app.selected_timer_tab)
         .alignment(Alignment::Right)
-        .highlight_style(Style::default().fg(Color::Green));
+        .highlight_style(highlight_style);
 
     let mut b = Block::default()
         .borders(Borders::RIGHT | Borders::TOP | Borders::BOTTOM)
@@ -138,73 +352,345 @@ fn render_timer_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool,
     f.render_widget(timer_options_tabs, area);
 }
 
-fn render_header_widgets<B: Backend>(f: &mut Frame<B>, options_area: Rect, timer_area: Rect, app: &App) {
-    match app.focused_window {
-        FocusedWindow::Game => {
-            render_game_options(f, options_area, false, app);
-            render_timer_options(f, timer_area, false, app);
-        },
-        FocusedWindow::GameOptions => {
-            render_game_options(f, options_area, true, app);
-            render_timer_options(f, timer_area, false, app);
+fn render_modifier_options<B: Backend>(f: &mut Frame<B>, area: Rect, focused: bool, app: &App) {
+    // enabled modifiers are green, quotes and custom text are typed as written
+    // so they're greyed out
+    let options = app.modifier_options
+        .iter()
+        .map(|m| {
+            let style = match app.mode() {
+                Mode::Quote | Mode::Custom | Mode::Drill | Mode::Daily => Style::default().fg(Color::DarkGray),
+                _ if app.modifier_enabled(m) => Style::default().fg(Color::Green),
+                _ => Style::default()
+            };
+            Spans::from(Span::styled(*m, style))
+        })
+        .collect();
+
+    // the selected tab is the one space toggles, so only mark it while focused
+    let highlight_style = if focused {
+        Style::default().add_modifier(Modifier::UNDERLINED)
+    } else {
+        Style::default()
+    };
+
+    let mut modifier_options_tabs = AlignedTabs::new(options)
+        .select(app.selected_modifier_tab)
+        .alignment(Alignment::Center)
+        .highlight_style(highlight_style);
+
+    let mut b = Block::default()
+        .borders(Borders::TOP | Borders::BOTTOM)
+        .border_type(BorderType::Rounded);
+
+    if focused {
+        b = b.border_style(Style::default().fg(Color::Red));
+    }
+
+    modifier_options_tabs = modifier_options_tabs.block(b);
+    f.render_widget(modifier_options_tabs, area);
+}
+
+fn render_personal_best<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let text = match app.personal_best() {
+        Some(r) => format!("pb {:.0} wpm", r.wpm),
+        None => "no pb".to_string()
+    };
+
+    let pb = Paragraph::new(Span::styled(text, Style::default().fg(Color::Blue)))
+        .block(Block::default().borders(Borders::TOP | Borders::BOTTOM).border_type(BorderType::Rounded))
+        .alignment(Alignment::Center);
+    f.render_widget(pb, area);
+}
+
+fn lesson_spans(lesson: &Lesson) -> Spans<'static> {
+    let mut spans = vec![Span::styled("keys", Style::default().fg(Color::Blue))];
+    for c in &lesson.targets {
+        spans.push(Span::styled(format!(" {}", c), Style::default().fg(Color::Yellow)));
+    }
+    for (a, b) in &lesson.bigrams {
+        spans.push(Span::styled(format!(" {}{}", a, b), Style::default().fg(Color::Red)));
+    }
+    Spans::from(spans)
+}
+
+fn lesson_width(lesson: &Lesson) -> u16 {
+    lesson_spans(lesson).width() as u16 + 2
+}
+
+fn render_lesson<B: Backend>(f: &mut Frame<B>, area: Rect, lesson: &Lesson) {
+    let keys = Paragraph::new(lesson_spans(lesson))
+        .block(Block::default().borders(Borders::TOP | Borders::BOTTOM).border_type(BorderType::Rounded))
+        .alignment(Alignment::Center);
+    f.render_widget(keys, area);
+}
+
+fn render_header_widgets<B: Backend>(f: &mut Frame<B>, header_chunks: &[Rect], app: &App) {
+    let focused = &app.focused_window;
+    render_mode_options(f, header_chunks[0], matches!(focused, FocusedWindow::ModeOptions), app);
+    render_game_options(f, header_chunks[1], matches!(focused, FocusedWindow::GameOptions), app);
+
+    // training shares the word list chunk with the letters it is working on
+    match &app.lesson {
+        Some(lesson) => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(lesson_width(lesson))].as_ref())
+                .split(header_chunks[2]);
+            render_word_list_options(f, chunks[0], matches!(focused, FocusedWindow::WordListOptions), app);
+            render_lesson(f, chunks[1], lesson);
         },
-        FocusedWindow::TimerOptions => {
-            render_game_options(f, options_area, false, app);
-            render_timer_options(f, timer_area, true, app);
-        }
+        None => render_word_list_options(f, header_chunks[2], matches!(focused, FocusedWindow::WordListOptions), app)
     }
+    render_modifier_options(f, header_chunks[3], matches!(focused, FocusedWindow::ModifierOptions), app);
+    render_personal_best(f, header_chunks[4], app);
+    render_timer_options(f, header_chunks[5], matches!(focused, FocusedWindow::TimerOptions), app);
 }
 
-// fn render_timer<B: Backend> (f: &mut Frame<B>, timer_area: Rect, app: &App) {
-//     let timer = Paragraph::new(time.to_string())
-//         .alignment(Alignment::Left);
-//     f.render_widget(timer, timer_area);
-// }
+fn render_timer<B: Backend> (f: &mut Frame<B>, timer_area: Rect, app: &App) {
+    let countdown = app.race.as_ref().and_then(|r| r.starts_at);
+    let text = match (countdown, app.word_target()) {
+        (Some(t), _) => format!("starting in {}", t.saturating_duration_since(Instant::now()).as_secs() + 1),
+        (None, Some(n)) => format!("{}/{}  {}s", app.session.correct_words(), n, app.clock),
+        (None, None) => app.clock.to_string()
+    };
+
+    let timer = Paragraph::new(text)
+        .style(Style::default().fg(Color::Blue))
+        .alignment(Alignment::Left);
+    f.render_widget(timer, timer_area);
+}
+
+// one bar per player stacked above the timer. Tests that end with the text
+// fill the bar over the whole text, timed tests fill it relative to whoever
+// is furthest along.
+fn render_race<B: Backend>(f: &mut Frame<B>, area: Rect, timer_area: Rect, app: &App) {
+    let race = match &app.race {
+        Some(r) => r,
+        None => return
+    };
+
+    let top = timer_area.y.saturating_sub(race.players.len() as u16 + 1).max(area.y);
+    let height = timer_area.y.saturating_sub(top + 1);
+    let race_area = Rect {
+        y: top,
+        height,
+        ..timer_area
+    };
+
+    let total = match app.countdown() {
+        Some(_) => race.players.iter().map(|p| p.progress.cursor).max().unwrap_or(0),
+        None => app.session.text_len()
+    }.max(1);
+
+    let name_width = race.players.iter().map(|p| p.name.chars().count()).max().unwrap_or(0);
+    // name, a space, the bar, and the error count after it
+    let bar_width = (race_area.width as usize).saturating_sub(name_width + 12);
+
+    let lines: Vec<Spans> = race.players
+        .iter()
+        .map(|p| {
+            let filled = (p.progress.cursor.min(total) * bar_width) / total;
+            let color = if p.progress.done { Color::Green } else { Color::Blue };
+            Spans::from(vec![
+                Span::raw(format!("{:width$} ", p.name, width = name_width)),
+                Span::styled("█".repeat(filled), Style::default().fg(color)),
+                Span::styled("░".repeat(bar_width - filled), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!(" {} err", p.progress.errors), Style::default().fg(Color::Red)),
+            ])
+        })
+        .collect();
+
+    f.render_widget(Paragraph::new(lines), race_area);
+}
 
 fn render_typing_section<B: Backend> (f: &mut Frame<B>, typing_area: Rect, app: &mut App) {
-    let text_bytes: Vec<char> = app.game_text.chars().collect();
-    let my_text_bytes: Vec<char> = app.my_game_text.chars().collect();
-    let mut c_index: usize = 0;
-    let mut para = vec![];
-
-    for (i, c) in my_text_bytes.iter().enumerate() {
-        c_index = i + 1;
-        if c == &text_bytes[i] {
-            //let c = my_text_bytes[i] as char;
-            let c = c.to_string();
-            let s = Span::styled(c, Style::default().fg(Color::Green));
-            para.push(s);
-        } else if *c == '\0' {
-            let c = text_bytes[i];
-            let c = c.to_string();
-            let s = Span::styled(c, Style::default().fg(Color::Yellow));
-            para.push(s);
+    if app.game_text.is_empty() {
+        // only a drill can come up empty
+        let text = if app.problem_words.words.is_empty() {
+            "no problem words yet"
         } else {
-            let c = text_bytes[i];
-            let c = c.to_string();
-            let s = Span::styled(c, Style::default().fg(Color::Red));
-            para.push(s);
+            "nothing due yet, pick all to drill every word"
+        };
+        let message = Paragraph::new(Span::styled(text, Style::default().fg(Color::DarkGray)));
+        f.render_widget(message, typing_area);
+        return;
+    }
+
+    let width = typing_area.width as usize;
+    let extra_chars = app.session.extra_chars();
+    let layout = match app.typing_layout.take() {
+        Some(l) if l.fits(&app.game_text, extra_chars, width) => l,
+        _ => TypingLayout::new(&app.game_text, extra_chars, width)
+    };
+    let session = &app.session;
+    let c_index = session.cursor().min(layout.places.len() - 1);
+    let ghost_index = app.ghost_position();
+
+    // the view scrolls a line at a time so the caret never sits on the last
+    // visible line, leaving the next line always in view
+    let line_starts = &layout.line_starts;
+    let height = typing_area.height as usize;
+    let caret_cell = layout.place_cells[c_index];
+    let caret_line = line_starts.partition_point(|s| *s <= caret_cell) - 1;
+    let first_line = caret_line.saturating_sub(height.saturating_sub(2));
+    let last_line = (first_line + height).min(line_starts.len());
+
+    let mut lines = vec![];
+    for l in first_line..last_line {
+        let end = line_starts.get(l + 1).cloned().unwrap_or(layout.cells.len());
+        let mut para = vec![];
+
+        for cell in &layout.cells[line_starts[l]..end] {
+            let i = match cell {
+                Cell::Text(i) => *i,
+                Cell::Extra(g) => {
+                    para.push(Span::styled(g.clone(), Style::default().fg(Color::Red)));
+                    continue;
+                }
+            };
+
+            let g = layout.grapheme(i);
+            let style = if i == c_index {
+                Style::default().bg(Color::DarkGray).fg(Color::Black)
+            } else if Some(i) == ghost_index {
+                Style::default().bg(Color::Magenta).fg(Color::Black)
+            } else {
+                match session.typed_at(i) {
+                    None => Style::default(),
+                    Some(typed) if session.rules().comparison.matches(typed, g) => Style::default().fg(Color::Green),
+                    Some("\0") => Style::default().fg(Color::Yellow),
+                    Some(_) => Style::default().fg(Color::Red)
+                }
+            };
+
+            // newlines get a marker so the caret and mistakes on them are
+            // still visible
+            let content = match g {
+                "\n" => "↵".to_string(),
+                g => g.to_string()
+            };
+            para.push(Span::styled(content, style));
         }
+
+        lines.push(Spans::from(para));
     }
 
-    para.push({
-        let content: String = text_bytes[c_index..c_index + 1].iter().collect();
-        let style = Style::default().bg(Color::DarkGray).fg(Color::Black);
-        Span {
-            content: content.into(),
-            style,
+    let typing_section = Paragraph::new(lines)
+        .alignment(Alignment::Left);
+    f.render_widget(typing_section, typing_area);
+    app.typing_layout = Some(layout);
+}
+
+/// The typing text split into cells and word wrapped, kept between frames so
+/// a long text isn't split and wrapped again on every tick.
+pub struct TypingLayout {
+    text: String,
+    extra_chars: HashMap<usize, String>,
+    width: usize,
+    places: Vec<Range<usize>>,
+    cells: Vec<Cell>,
+    /// The cell each place of the text is drawn in.
+    place_cells: Vec<usize>,
+    line_starts: Vec<usize>,
+}
+
+// extra letters are drawn in front of the space they were typed on,
+// pushing the rest of the text along
+enum Cell {
+    Text(usize),
+    Extra(String)
+}
+
+impl TypingLayout {
+    fn new(text: &str, extra_chars: &HashMap<usize, String>, width: usize) -> Self {
+        let places = text::grapheme_ranges(text);
+        let mut cells = vec![];
+        let mut place_cells = vec![];
+        for i in 0..places.len() {
+            if let Some(extra) = extra_chars.get(&i) {
+                cells.extend(graphemes(extra).into_iter().map(|e| Cell::Extra(e.to_string())));
+            }
+            place_cells.push(cells.len());
+            cells.push(Cell::Text(i));
         }
-    });
-    para.push({
-        let content: String = text_bytes[c_index + 1..].iter().collect();
-        Span {
-            content: content.into(),
-            style: Style::default(),
+
+        let cell_graphemes: Vec<&str> = cells
+            .iter()
+            .map(|c| match c {
+                Cell::Text(i) => &text[places[*i].clone()],
+                Cell::Extra(g) => g.as_str()
+            })
+            .collect();
+        let line_starts = wrap_lines(&cell_graphemes, width);
+
+        TypingLayout {
+            text: text.to_string(),
+            extra_chars: extra_chars.clone(),
+            width,
+            places,
+            cells,
+            place_cells,
+            line_starts,
         }
-    });
+    }
 
-    let typing_section = Paragraph::new(Spans::from(para))
-        .alignment(Alignment::Left)
-        .wrap(Wrap{ trim: true });
-    f.render_widget(typing_section, typing_area);
+    // comparing is cheap next to splitting and wrapping again
+    fn fits(&self, text: &str, extra_chars: &HashMap<usize, String>, width: usize) -> bool {
+        self.width == width && self.text == text && self.extra_chars == *extra_chars
+    }
+
+    fn grapheme(&self, place: usize) -> &str {
+        &self.text[self.places[place].clone()]
+    }
+}
+
+/// Word wraps `text` to `width` columns, returning the index each line starts at.
+///
+/// Spaces stay at the end of the line before a break and newlines always end
+/// a line, so every grapheme, caret included, has a cell to be drawn in. Wide
+/// graphemes take up two columns.
+fn wrap_lines(text: &[&str], width: usize) -> Vec<usize> {
+    let width = width.max(1);
+    let mut line_starts = vec![0];
+    let mut line_len = 0;
+    let mut i = 0;
+
+    while i < text.len() {
+        // a word, the spaces after it, and the newline ending it if any
+        let start = i;
+        while i < text.len() && text[i] != " " && text[i] != "\n" {
+            i += 1;
+        }
+        while i < text.len() && text[i] == " " {
+            i += 1;
+        }
+        let newline = i < text.len() && text[i] == "\n";
+        if newline {
+            i += 1;
+        }
+
+        let word_len: usize = text[start..i].iter().map(|g| text::width(g)).sum();
+        if line_len > 0 && line_len + word_len > width {
+            line_starts.push(start);
+            line_len = 0;
+        }
+
+        // words longer than a line are split wherever they run out of room
+        for (j, g) in text.iter().enumerate().take(i).skip(start) {
+            let w = text::width(g);
+            if line_len > 0 && line_len + w > width {
+                line_starts.push(j);
+                line_len = 0;
+            }
+            line_len += w;
+        }
+
+        if newline && i < text.len() {
+            line_starts.push(i);
+            line_len = 0;
+        }
+    }
+
+    line_starts
 }
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Widget},
+};
+
+const ROWS: [&str; 4] = ["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+const ROW_OFFSETS: [u16; 4] = [0, 2, 3, 4];
+const KEY_WIDTH: u16 = 4;
+const SPACE_OFFSET: u16 = 12;
+const SPACE_WIDTH: u16 = 27;
+
+/// A QWERTY keyboard heatmap, each key colored by a value from 0 to 1.
+///
+/// Keys without a value are drawn greyed out. Values under a third are green,
+/// under two thirds yellow, and red above that.
+///
+/// # Examples
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use tui::widgets::{Block, Borders};
+/// let mut values = HashMap::new();
+/// values.insert('e', 0.1);
+/// values.insert('q', 0.9);
+/// Keyboard::new(values)
+///     .block(Block::default().title("errors").borders(Borders::ALL));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Keyboard<'a> {
+    /// A block to wrap this widget in if necessary
+    block: Option<Block<'a>>,
+    /// Value for each key, by its unshifted character
+    values: HashMap<char, f64>,
+}
+
+impl<'a> Keyboard<'a> {
+    pub fn new(values: HashMap<char, f64>) -> Keyboard<'a> {
+        Keyboard {
+            block: None,
+            values,
+        }
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Keyboard<'a> {
+        self.block = Some(block);
+        self
+    }
+
+    /// Total size the keys take up, without the block.
+    pub fn size() -> (u16, u16) {
+        let width = ROWS
+            .iter()
+            .zip(ROW_OFFSETS.iter())
+            .map(|(row, offset)| offset + row.len() as u16 * KEY_WIDTH)
+            .max()
+            .unwrap_or(0);
+        (width, ROWS.len() as u16 + 1)
+    }
+
+    fn style(&self, key: char) -> Style {
+        match self.values.get(&key) {
+            None => Style::default().fg(Color::DarkGray),
+            Some(v) if *v < 1.0 / 3.0 => Style::default().bg(Color::Green).fg(Color::Black),
+            Some(v) if *v < 2.0 / 3.0 => Style::default().bg(Color::Yellow).fg(Color::Black),
+            Some(_) => Style::default().bg(Color::Red).fg(Color::Black),
+        }
+    }
+}
+
+impl<'a> Widget for Keyboard<'a> {
+    fn render(mut self, area: Rect, buf: &mut Buffer) {
+        let keys_area = match self.block.take() {
+            Some(b) => {
+                let inner_area = b.inner(area);
+                b.render(area, buf);
+                inner_area
+            }
+            None => area,
+        };
+
+        let (width, height) = Keyboard::size();
+        if keys_area.width < width || keys_area.height < height {
+            return;
+        }
+
+        // keep the keys centered in whatever room there is
+        let left = keys_area.left() + (keys_area.width - width) / 2;
+        let top = keys_area.top() + (keys_area.height - height) / 2;
+
+        for (y, (row, offset)) in ROWS.iter().zip(ROW_OFFSETS.iter()).enumerate() {
+            for (i, key) in row.chars().enumerate() {
+                let x = left + offset + i as u16 * KEY_WIDTH;
+                buf.set_string(x, top + y as u16, format!(" {} ", key), self.style(key));
+            }
+        }
+
+        let space = " ".repeat(SPACE_WIDTH as usize);
+        buf.set_string(left + SPACE_OFFSET, top + ROWS.len() as u16, space, self.style(' '));
+    }
+}
+
+/// The key a character is typed with, so shifted characters count towards
+/// the key they share.
+pub fn base_key(c: char) -> char {
+    match c {
+        '~' => '`',
+        '!' => '1',
+        '@' => '2',
+        '#' => '3',
+        '$' => '4',
+        '%' => '5',
+        '^' => '6',
+        '&' => '7',
+        '*' => '8',
+        '(' => '9',
+        ')' => '0',
+        '_' => '-',
+        '+' => '=',
+        '{' => '[',
+        '}' => ']',
+        '|' => '\\',
+        ':' => ';',
+        '"' => '\'',
+        '<' => ',',
+        '>' => '.',
+        '?' => '/',
+        c => c.to_ascii_lowercase(),
+    }
+}
@@ -1 +1,2 @@
 pub mod alignedtabs;
+pub mod keyboard;
@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    io,
+    path::Path
+};
+
+use super::paths::data_dir;
+
+const DEFAULT_WORDS: &str = include_str!("words.txt");
+
+/// A named list of words to generate tests from.
+pub struct WordList {
+    pub name: String,
+    pub words: Vec<String>,
+}
+
+impl WordList {
+    /// The english list that ships with rype.
+    pub fn embedded() -> Self {
+        WordList::parse("english", DEFAULT_WORDS)
+    }
+
+    /// Reads a newline-delimited word file, named after its file stem.
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "custom".to_string());
+
+        let list = WordList::parse(&name, &contents);
+        if list.words.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} contains no words", path.display())
+            ));
+        }
+
+        Ok(list)
+    }
+
+    fn parse(name: &str, contents: &str) -> Self {
+        WordList {
+            name: name.to_string(),
+            words: contents
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// The embedded list followed by every list in `<data dir>/wordlists`.
+pub fn load_word_lists() -> io::Result<Vec<WordList>> {
+    let mut lists = vec![WordList::embedded()];
+
+    let dir = match data_dir() {
+        Some(d) => d.join("wordlists"),
+        None => return Ok(lists)
+    };
+
+    let entries = match fs::read_dir(&dir) {
+        Ok(e) => e,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(lists),
+        Err(e) => return Err(e),
+    };
+
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    for path in paths {
+        // a bad file shouldn't keep the game from starting
+        if let Ok(list) = WordList::from_file(&path) {
+            lists.push(list);
+        }
+    }
+
+    Ok(lists)
+}